@@ -0,0 +1,71 @@
+//! Writes seed inputs for the fuzz targets into `corpus/<target>`, from
+//! stores generated with the fake gpg used by the tests.
+
+use pass::internal::{Attachment, Database, Header, SizedWrite};
+
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+const KEY: &str = "fuzz@example.com";
+
+fn databases() -> Vec<Database> {
+    let empty = Database::new(Some(KEY.to_string()));
+
+    let mut small = Database::new(Some(KEY.to_string()));
+    small.insert("mail", "s3cret");
+
+    let mut full = Database::new(Some(KEY.to_string()));
+    full.insert("web/github", "hunter2\nuser: alice\ntags: prod, ops");
+    full.update("web/github", "hunter3\nuser: alice\ntags: prod, ops");
+    full.insert("bank", "1234\nrotate: 90d\nexpires: 2030-01-01");
+    full.insert("old", "gone");
+    full.remove("old");
+    full.insert("server", "pw");
+    let key = b"-----BEGIN KEY-----\nAAAA\n-----END KEY-----\n".repeat(20);
+    full.entry_mut("server").unwrap().attach(Attachment::new("id", key));
+
+    vec![empty, small, full]
+}
+
+fn write(dir: &Path, name: &str, data: &[u8]) {
+    fs::write(dir.join(name), data).unwrap();
+}
+
+fn main() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    if env::var_os("PASS_GPG").is_none() {
+        env::set_var("PASS_GPG", root.join("../tests/fake-gpg"));
+    }
+    let dirs: Vec<PathBuf> = ["preamble", "header", "body"]
+        .iter()
+        .map(|v| root.join("corpus").join(v))
+        .collect();
+    for dir in &dirs {
+        fs::create_dir_all(dir).unwrap();
+    }
+    let [preamble, header, body] = &dirs[..] else { unreachable!() };
+
+    let store = root.join("target/corpus.store");
+    for (i, db) in databases().iter().enumerate() {
+        db.write_to_file(&store).unwrap();
+        let bytes = fs::read(&store).unwrap();
+        write(preamble, &format!("store-{i}"), &bytes);
+
+        // the same store in format version 1
+        let mut reader = std::io::Cursor::new(&bytes);
+        let found = Database::read_preamble(&mut reader).unwrap();
+        let mut v1 = vec![];
+        v1.sized_write(found.gpg_id.as_bytes()).unwrap();
+        v1.sized_write(&found.header).unwrap();
+        v1.extend_from_slice(&bytes[reader.position() as usize..]);
+        write(preamble, &format!("store-{i}-v1"), &v1);
+
+        write(body, &format!("body-{i}"), &serde_json::to_vec(db).unwrap());
+    }
+    write(body, "legacy", br#"{"pairs":{"mail":"s3cret"}}"#);
+
+    write(header, "generated", Header::generate().as_bytes());
+    write(header, "short", &[7; 43]);
+
+    println!("Wrote seed corpus to {}", root.join("corpus").display());
+}
@@ -0,0 +1,14 @@
+//! Decrypted bodies. Whatever parses must survive being written back.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pass::internal::Database;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(db) = Database::from_body(data) {
+        let written = serde_json::to_vec(&db).unwrap();
+        let again = Database::from_body(&written).unwrap();
+        assert_eq!(again.count(), db.count());
+    }
+});
@@ -0,0 +1,13 @@
+//! Decrypted headers, as returned by gpg or the unlock agent.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pass::internal::Header;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(header) = Header::try_from(data) {
+        assert_eq!(header.as_bytes(), data);
+        let _ = header.cipher();
+    }
+});
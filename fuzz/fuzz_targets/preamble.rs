@@ -0,0 +1,16 @@
+//! The unencrypted start of a store: format marker, key id and header
+//! frames.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pass::internal::Database;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    let mut reader = Cursor::new(data);
+    if let Ok(preamble) = Database::read_preamble(&mut reader) {
+        assert!(reader.position() <= data.len() as u64);
+        assert!(preamble.header.len() <= data.len());
+    }
+});
@@ -0,0 +1,261 @@
+//! The unlock agent: a per-user background process that keeps decrypted
+//! store headers in locked memory, so that reading the store does not
+//! need a GPG decrypt every time. Headers are wiped after an idle
+//! timeout, or immediately with `lock`.
+//!
+//! Requests and responses are `sized_io` frames on a Unix socket. The
+//! first byte of a request is its operation.
+//!
+//! Headers are only sent to the agent once it is enabled with
+//! `set_enabled`, and only over a socket in a directory private to this
+//! user, with this user's process listening on it.
+
+use crate::header::{Header, HEADER_BYTE_LEN};
+use crate::sized_io::{SizedRead, SizedWrite, MAX_FRAME};
+#[cfg(target_os = "linux")]
+use std::ptr;
+
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{env, fs, thread};
+use zeroize::Zeroizing;
+
+const GET: u8 = 1;
+const PUT: u8 = 2;
+const LOCK: u8 = 3;
+const PING: u8 = 4;
+
+const OK: u8 = 0;
+const MISS: u8 = 1;
+
+/// Most headers the agent holds at once, one per store.
+const CAPACITY: usize = 16;
+
+/// Default idle timeout in seconds
+pub const DEFAULT_TIMEOUT: u64 = 15 * 60;
+
+/// Whether `get` and `put` talk to the agent at all
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Let reads and writes of a store use the agent. Off by default.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+/// Path of the agent socket, in a directory only this user can read.
+pub fn socket_path() -> PathBuf {
+    let dir = match env::var_os("XDG_RUNTIME_DIR") {
+        Some(v) => PathBuf::from(v),
+        None => env::temp_dir().join(format!("pass-{}", uid())),
+    };
+    dir.join("pass-agent.sock")
+}
+
+/// Fail unless `dir` is a directory of this user with mode 0700, so that
+/// no one else can put a socket of their own in it.
+fn check_dir(dir: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(dir)?;
+    if !meta.is_dir() || meta.uid() != uid() || meta.mode() & 0o777 != 0o700 {
+        let msg = format!(
+            "{} must be a directory owned by you with mode 0700",
+            dir.display()
+        );
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, msg));
+    }
+    Ok(())
+}
+
+fn connect() -> io::Result<UnixStream> {
+    let path = socket_path();
+    check_dir(path.parent().unwrap_or(Path::new("/")))?;
+    let stream = UnixStream::connect(&path)?;
+    if peer_uid(&stream)? != uid() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the agent socket is held by another user",
+        ));
+    }
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    Ok(stream)
+}
+
+fn request(op: u8, payload: &[&[u8]]) -> io::Result<Vec<u8>> {
+    let mut stream = connect()?;
+    stream.sized_write_u32(&[op])?;
+    for part in payload {
+        stream.sized_write_u32(part)?;
+    }
+    stream.sized_read_u32(MAX_FRAME)
+}
+
+/// Whether an agent is listening
+pub fn is_running() -> bool {
+    request(PING, &[]).is_ok()
+}
+
+/// The decrypted header for `encrypted`, if the agent is enabled and
+/// holds it.
+pub fn get(encrypted: &[u8]) -> Option<Header> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let response = Zeroizing::new(request(GET, &[encrypted]).ok()?);
+    match response.split_first() {
+        Some((&OK, data)) => Header::try_from(data).ok(),
+        _ => None,
+    }
+}
+
+/// Hand a decrypted header to the agent. Does nothing if the agent is
+/// not enabled or not running.
+pub fn put(encrypted: &[u8], header: &Header) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let _ = request(PUT, &[encrypted, header.as_bytes()]);
+}
+
+/// Wipe every header held by the agent and stop it.
+/// Returns `false` if no agent was running.
+pub fn lock() -> bool {
+    request(LOCK, &[]).is_ok()
+}
+
+struct State {
+    headers: Vec<(Vec<u8>, Header)>,
+    last_used: Instant,
+}
+
+/// Run the agent in the foreground until it is locked or has been idle
+/// for `timeout`.
+pub fn serve(timeout: Duration) -> io::Result<()> {
+    let path = socket_path();
+    let dir = path.parent().unwrap_or(Path::new("/"));
+    if !dir.exists() {
+        fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    }
+    check_dir(dir)?;
+    if is_running() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            "an agent is already running",
+        ));
+    }
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+
+    let state = Arc::new(Mutex::new(State {
+        headers: vec![],
+        last_used: Instant::now(),
+    }));
+
+    let watched = Arc::clone(&state);
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(1));
+        if watched.lock().unwrap().last_used.elapsed() >= timeout {
+            shutdown(&watched);
+        }
+    });
+
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { continue };
+        if peer_uid(&stream).ok() != Some(uid()) {
+            continue;
+        }
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+        let _ = handle(&mut stream, &state);
+    }
+    Ok(())
+}
+
+fn handle(
+    stream: &mut UnixStream,
+    state: &Arc<Mutex<State>>,
+) -> io::Result<()> {
+    let op = stream.sized_read_u32(MAX_FRAME)?;
+    let mut guard = state.lock().unwrap();
+    guard.last_used = Instant::now();
+    match op.first() {
+        Some(&GET) => {
+            let encrypted = stream.sized_read_u32(MAX_FRAME)?;
+            let found = guard.headers.iter().find(|v| v.0 == encrypted);
+            match found {
+                Some((_, header)) => {
+                    let mut response =
+                        Zeroizing::new(Vec::with_capacity(1 + HEADER_BYTE_LEN));
+                    response.push(OK);
+                    response.extend_from_slice(header.as_bytes());
+                    stream.sized_write_u32(&response)
+                }
+                None => stream.sized_write_u32(&[MISS]),
+            }
+        }
+        Some(&PUT) => {
+            let encrypted = stream.sized_read_u32(MAX_FRAME)?;
+            let data = Zeroizing::new(stream.sized_read_u32(MAX_FRAME)?);
+            if let Ok(header) = Header::try_from(&data[..]) {
+                guard.headers.retain(|v| v.0 != encrypted);
+                if guard.headers.len() >= CAPACITY {
+                    guard.headers.remove(0);
+                }
+                guard.headers.push((encrypted, header));
+            }
+            stream.sized_write_u32(&[OK])
+        }
+        Some(&LOCK) => {
+            stream.sized_write_u32(&[OK])?;
+            drop(guard);
+            shutdown(state)
+        }
+        Some(&PING) => stream.sized_write_u32(&[OK]),
+        _ => stream.sized_write_u32(&[MISS]),
+    }
+}
+
+/// Wipe every header and exit.
+fn shutdown(state: &Arc<Mutex<State>>) -> ! {
+    state.lock().unwrap().headers.clear();
+    let _ = fs::remove_file(socket_path());
+    std::process::exit(0)
+}
+
+/// User id of the process on the other end of a socket
+#[cfg(target_os = "linux")]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            ptr::addr_of_mut!(cred).cast(),
+            &mut len,
+        )
+    };
+    match result {
+        0 => Ok(cred.uid),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// User id of the process on the other end of a socket
+#[cfg(not(target_os = "linux"))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let (mut uid, mut gid) = (0, 0);
+    match unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } {
+        0 => Ok(uid),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
@@ -0,0 +1,159 @@
+//! Files kept with an entry, such as certificates, key files or recovery
+//! codes. They live in the encrypted body like everything else,
+//! deflated and base64 encoded.
+
+use crate::timestamp;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::Cell;
+use std::io::{Read, Write};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Largest attachment kept, before compression: 64 MiB. Anything bigger
+/// in a body is rejected rather than inflated.
+pub const MAX_SIZE: usize = 64 << 20;
+
+/// Largest total of all attachments in a store, before compression:
+/// 256 MiB.
+pub const MAX_TOTAL_SIZE: u64 = 256 << 20;
+
+thread_local! {
+    /// Bytes that attachments being read may still inflate to. Only
+    /// limited inside `with_budget`.
+    static BUDGET: Cell<u64> = const { Cell::new(u64::MAX) };
+}
+
+/// Run `read` with every attachment it deserializes sharing a budget of
+/// `limit` bytes once inflated, so that a body cannot hold many
+/// attachments that are each just under `MAX_SIZE`.
+pub fn with_budget<T>(limit: u64, read: impl FnOnce() -> T) -> T {
+    let previous = BUDGET.replace(limit);
+    let result = read();
+    BUDGET.set(previous);
+    result
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Zeroize, ZeroizeOnDrop)]
+pub struct Attachment {
+    pub name: String,
+    pub added: u64,
+    #[serde(serialize_with = "encode", deserialize_with = "decode")]
+    data: Vec<u8>,
+}
+
+impl Attachment {
+    pub fn new(name: &str, data: Vec<u8>) -> Self {
+        Self { name: name.to_string(), added: timestamp::now(), data }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Size in bytes, before compression
+    pub fn size(&self) -> u64 {
+        self.data.len() as u64
+    }
+}
+
+fn encode<S: Serializer>(data: &[u8], s: S) -> Result<S::Ok, S::Error> {
+    let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+    encoder
+        .write_all(data)
+        .and_then(|_| encoder.finish())
+        .map(Zeroizing::new)
+        .map_err(serde::ser::Error::custom)
+        .and_then(|v| s.serialize_str(&Zeroizing::new(BASE64.encode(&*v))))
+}
+
+fn decode<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+    let text = Zeroizing::new(String::deserialize(d)?);
+    let deflated =
+        Zeroizing::new(BASE64.decode(&*text).map_err(de::Error::custom)?);
+    let budget = BUDGET.get();
+    let limit = budget.min(MAX_SIZE as u64);
+    let mut data = Zeroizing::new(vec![]);
+    DeflateDecoder::new(&deflated[..])
+        .take(limit + 1)
+        .read_to_end(&mut data)
+        .map_err(de::Error::custom)?;
+    if data.len() as u64 > limit {
+        return Err(de::Error::custom(match limit < MAX_SIZE as u64 {
+            true => "attachments are too large in total",
+            false => "attachment is too large",
+        }));
+    }
+    BUDGET.set(budget - data.len() as u64);
+    Ok(std::mem::take(&mut *data))
+}
+
+/// A byte count for people, e.g. `3.4 KiB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{size:.1} {}", UNITS[unit]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_survives_serialization() {
+        let data: Vec<u8> = (0..=255).cycle().take(3000).collect();
+        let attachment = Attachment::new("key.pem", data.clone());
+        let json = serde_json::to_string(&attachment).unwrap();
+        let read: Attachment = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.data(), data);
+        assert_eq!(read.size(), 3000);
+    }
+
+    #[test]
+    fn rejects_oversized_data() {
+        let mut encoder = DeflateEncoder::new(vec![], Compression::fast());
+        encoder.write_all(&vec![0; MAX_SIZE + 1]).unwrap();
+        let data = BASE64.encode(encoder.finish().unwrap());
+        let json = format!(r#"{{"name":"a","added":0,"data":"{data}"}}"#);
+        let error = serde_json::from_str::<Attachment>(&json).unwrap_err();
+        assert!(error.to_string().contains("too large"));
+    }
+
+    #[test]
+    fn budget_is_shared() {
+        let attachment = Attachment::new("a", vec![7; 1000]);
+        let json = serde_json::to_string(&[&attachment, &attachment]).unwrap();
+        let read = |limit| {
+            with_budget(limit, || {
+                serde_json::from_str::<Vec<Attachment>>(&json)
+            })
+        };
+        assert_eq!(read(2000).unwrap().len(), 2);
+        let error = read(1999).unwrap_err();
+        assert!(error.to_string().contains("too large in total"));
+
+        // the budget ends with the read
+        assert!(read(2000).is_ok());
+        assert!(serde_json::from_str::<Vec<Attachment>>(&json).is_ok());
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(3482), "3.4 KiB");
+        assert_eq!(format_size(5 << 30), "5.0 GiB");
+    }
+}
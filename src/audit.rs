@@ -0,0 +1,182 @@
+//! Password health checks: weak, reused, old, short, dictionary and
+//! breached passwords. Reports name entries only, never their passwords.
+
+use crate::entry::{self, Entry};
+use crate::hibp::HashFile;
+use crate::{Database, Result};
+
+use serde::Serialize;
+use std::collections::HashMap;
+use zxcvbn::matching::patterns::MatchPattern;
+use zxcvbn::zxcvbn;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Thresholds for an audit
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Passwords scoring below this (0 to 4) are weak
+    pub min_score: u8,
+    /// Passwords shorter than this are short
+    pub min_length: usize,
+    /// Passwords not changed for this many days are old
+    pub max_age_days: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self { min_score: 3, min_length: 12, max_age_days: 365 }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    /// Number of passwords checked
+    pub checked: usize,
+    pub weak: Vec<Weak>,
+    /// Groups of entries sharing a password
+    pub reused: Vec<Vec<String>>,
+    pub old: Vec<Old>,
+    pub short: Vec<Short>,
+    /// Entries whose password is made only of dictionary words
+    pub dictionary: Vec<String>,
+    /// Entries whose password is in a breach, if checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breached: Option<Vec<Breached>>,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.weak.is_empty()
+            && self.reused.is_empty()
+            && self.old.is_empty()
+            && self.short.is_empty()
+            && self.dictionary.is_empty()
+            && self.breached.as_ref().is_none_or(Vec::is_empty)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Weak {
+    pub name: String,
+    /// zxcvbn score, 0 to 4
+    pub score: u8,
+    /// Estimated guesses needed, as a power of ten
+    pub guesses_log10: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Old {
+    pub name: String,
+    /// When the password was last changed. 0 if unknown
+    pub modified: u64,
+    /// Days since then, if known
+    pub days: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Breached {
+    pub name: String,
+    /// Times the password was seen in breaches
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Short {
+    pub name: String,
+    pub length: usize,
+}
+
+/// Check the current password of every entry. Entries without a
+/// password (e.g. notes only) are skipped.
+pub fn audit(db: &Database, options: &Options, now: u64) -> Report {
+    let mut names = db.list_all();
+    names.sort();
+
+    let mut report = Report::default();
+    let mut by_password: HashMap<&str, Vec<String>> = HashMap::new();
+    for name in &names {
+        let entry = db.entry(name).unwrap();
+        let password = entry::password(entry.value());
+        if password.is_empty() {
+            continue;
+        }
+        report.checked += 1;
+        by_password.entry(password).or_default().push(name.clone());
+        check(&mut report, name, entry, options, now);
+    }
+
+    report.reused = by_password.into_values().filter(|v| v.len() > 1).collect();
+    report.reused.sort();
+    report
+}
+
+fn check(
+    report: &mut Report,
+    name: &str,
+    entry: &Entry,
+    options: &Options,
+    now: u64,
+) {
+    let password = entry::password(entry.value());
+
+    // parts of the name and the username make guessing easier
+    let mut inputs: Vec<&str> = name.split(['/', '.', '@', '-', '_']).collect();
+    let username = entry::field(entry.value(), "username");
+    inputs.extend(username.as_deref());
+
+    let estimate = zxcvbn(password, &inputs);
+    let score = estimate.score() as u8;
+    if score < options.min_score {
+        report.weak.push(Weak {
+            name: name.to_string(),
+            score,
+            guesses_log10: (estimate.guesses_log10() * 100.0).round() / 100.0,
+            warning: estimate
+                .feedback()
+                .and_then(|v| v.warning())
+                .map(|v| v.to_string()),
+        });
+    }
+
+    let sequence = estimate.sequence();
+    let words_only = !sequence.is_empty()
+        && sequence
+            .iter()
+            .all(|v| matches!(v.pattern, MatchPattern::Dictionary(_)));
+    if words_only {
+        report.dictionary.push(name.to_string());
+    }
+
+    let length = password.chars().count();
+    if length < options.min_length {
+        report.short.push(Short { name: name.to_string(), length });
+    }
+
+    let modified = entry.password_changed();
+    let days = (modified > 0).then(|| now.saturating_sub(modified) / DAY);
+    if days.is_none_or(|v| v >= options.max_age_days) {
+        report.old.push(Old { name: name.to_string(), modified, days });
+    }
+}
+
+/// Look up the current password of every entry in a Have I Been Pwned
+/// hash file.
+pub fn breached(db: &Database, file: &mut HashFile) -> Result<Vec<Breached>> {
+    let mut names = db.list_all();
+    names.sort();
+
+    let mut found = vec![];
+    for name in names {
+        let password = entry::password(db.get_unchecked(&name));
+        if password.is_empty() {
+            continue;
+        }
+        if let Some(count) = file.count(password)? {
+            found.push(Breached { name, count });
+        }
+    }
+    Ok(found)
+}
@@ -4,6 +4,7 @@ use chacha20::ChaCha20;
 use std::io;
 use std::io::{Read, Write};
 use std::result::Result as SResult;
+use zeroize::Zeroizing;
 
 /// A decryption warapper for `io::Read`.
 pub struct ChaReader<R: Read> {
@@ -19,9 +20,10 @@ impl<R: Read> ChaReader<R> {
 
 impl<R: Read> Read for ChaReader<R> {
     fn read(&mut self, buffer: &mut [u8]) -> SResult<usize, io::Error> {
-        let count_result = self.src.read(buffer);
-        self.cipher.apply_keystream(buffer);
-        count_result
+        // only decrypt what was read, so the keystream stays in step
+        let count = self.src.read(buffer)?;
+        self.cipher.apply_keystream(&mut buffer[..count]);
+        Ok(count)
     }
 }
 
@@ -38,10 +40,13 @@ impl<W: Write> ChaWriter<W> {
 }
 
 impl<W: Write> Write for ChaWriter<W> {
+    /// Encrypts and writes all of `buffer`: once the keystream has been
+    /// applied, the bytes cannot be handed back to the caller unwritten.
     fn write(&mut self, buffer: &[u8]) -> SResult<usize, io::Error> {
-        let mut buffer = buffer.to_vec();
-        self.cipher.apply_keystream(&mut buffer);
-        self.dst.write(&buffer)
+        let mut encrypted = Zeroizing::new(buffer.to_vec());
+        self.cipher.apply_keystream(&mut encrypted);
+        self.dst.write_all(&encrypted)?;
+        Ok(buffer.len())
     }
 
     fn flush(&mut self) -> SResult<(), io::Error> {
@@ -1,12 +1,25 @@
-use crate::{clipboard::clip, database::Database, error::Error};
+use crate::attachment::{self, Attachment};
+use crate::entry;
+use crate::expiry;
+use crate::export::{self, Format, Item};
+use crate::gpg::Gpg;
+use crate::hibp::HashFile;
+use crate::import::{self, Action, Conflict, Parsed, Preset, Record};
+use crate::otp::{self, Kind, Otp};
+use crate::{agent, audit, reference, ssh, template};
+use crate::{clipboard::clip, skim};
+use crate::{config, timestamp, Config, Database, Error, Store};
 
-use clap::{Parser, Subcommand};
+use clap::{Args as ClapArgs, Parser, Subcommand};
 use rand::{distributions::Alphanumeric, Rng};
 use rpassword::read_password;
 
 const LINE: &str = "──────────────────────────────";
 
-use std::{fs, io::Write, path::PathBuf, process::Command};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::{fs, io::Write, process::Command};
+use zeroize::Zeroizing;
 
 // The CLI app structure. The list of arguments available to the CLI user.
 #[derive(Parser, Debug)]
@@ -17,6 +30,24 @@ struct Args {
 
     /// Name/context of the password stored
     name: Option<String>,
+
+    /// Override a setting for this run, e.g. `-c output=print`
+    #[arg(
+        short = 'c',
+        long = "config",
+        global = true,
+        value_name = "KEY=VALUE",
+        value_parser = parse_setting
+    )]
+    overrides: Vec<(String, String)>,
+
+    /// Use a named store instead of the default one
+    #[arg(long, global = true, value_name = "NAME")]
+    store: Option<String>,
+
+    /// Use the store in this directory
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "store")]
+    store_path: Option<PathBuf>,
 }
 
 // The sub-commands available. These describe actions that the user can take
@@ -24,14 +55,62 @@ struct Args {
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Create a new password store
-    Init { gpg_id: String },
+    Init {
+        gpg_id: String,
+
+        /// Leave the key id out of the store file. Reading the store then
+        /// tries every secret key
+        #[arg(long)]
+        hide_key_id: bool,
+    },
 
     /// Move ownership to new GPG key id
     #[command(name = "re-init")]
-    Reinit { gpg_id: String },
+    Reinit {
+        gpg_id: String,
+
+        /// Leave the key id out of the store file
+        #[arg(long)]
+        hide_key_id: bool,
+
+        /// Write the key id in the store file again
+        #[arg(long, conflicts_with = "hide_key_id")]
+        show_key_id: bool,
+    },
+
+    /// List passwords, optionally only those with some tags
+    Ls {
+        /// Also show when each password changed and its attachments
+        #[arg(short, long)]
+        long: bool,
+
+        /// Only list passwords with this tag. Repeat to require several
+        #[arg(short, long)]
+        tag: Vec<String>,
+
+        /// Only list passwords with the tags of a saved filter
+        #[arg(short, long)]
+        filter: Option<String>,
+    },
+
+    /// Manage files attached to a password
+    Attach {
+        #[command(subcommand)]
+        command: AttachCommands,
+    },
+
+    /// Manage the tags of a password
+    Tag {
+        #[command(subcommand)]
+        command: TagCommands,
+    },
 
     /// Show the location of the current pass.store
-    Location,
+    Location {
+        /// Also show which setting chose it
+        #[arg(short, long)]
+        verbose: bool,
+    },
 
     /// Insert a new password
     Insert {
@@ -39,28 +118,455 @@ enum Commands {
 
         #[arg(short, long)]
         password: Option<String>,
+
+        /// Generate a random password
+        #[arg(short, long, conflicts_with = "password")]
+        generate: bool,
+
+        /// Length of the generated password. Defaults to
+        /// `generator.length`
+        #[arg(short, long, requires = "generate")]
+        length: Option<usize>,
     },
 
     /// Rename a password
     Move { current: String, next: String },
 
+    /// Copy the current value of passwords to another named store
+    #[command(name = "cp")]
+    Copy {
+        names: Vec<String>,
+
+        /// Name of the store to copy to
+        #[arg(long, value_name = "NAME")]
+        to_store: String,
+
+        /// Replace passwords that already exist in the other store. The
+        /// replaced ones are kept in its trash
+        #[arg(short, long)]
+        force: bool,
+
+        /// Also copy previous values
+        #[arg(long)]
+        with_history: bool,
+
+        /// Also copy attachments
+        #[arg(long)]
+        with_attachments: bool,
+    },
+
     /// Edit a password
     Edit { name: Option<String> },
 
     /// Remove a name-password pair
     Remove { name: Option<String> },
+
+    /// List previous versions of a password
+    Log { name: Option<String> },
+
+    /// Show a password, optionally at a previous version
+    Show {
+        name: Option<String>,
+
+        /// Version to show. 0 is the current value.
+        #[arg(short, long, default_value_t = 0)]
+        version: usize,
+    },
+
+    /// Make a previous version the current password
+    Restore { name: String, version: usize },
+
+    /// Manage removed passwords
+    Trash {
+        #[command(subcommand)]
+        command: Option<TrashCommands>,
+    },
+
+    /// Copy a one-time code to the clipboard
+    Otp {
+        #[command(subcommand)]
+        command: Option<OtpCommands>,
+
+        name: Option<String>,
+    },
+
+    /// Load an SSH private key into the running ssh-agent
+    #[command(name = "ssh-add")]
+    SshAdd {
+        name: Option<String>,
+
+        /// Remove the key from the agent after this many seconds
+        #[arg(short = 't', long)]
+        lifetime: Option<u32>,
+    },
+
+    /// Serve SSH private keys from the store as an ssh-agent
+    #[command(name = "ssh-agent")]
+    SshAgent {
+        /// Names of the keys to serve. Defaults to every key in the store
+        names: Vec<String>,
+
+        /// Path of the agent socket
+        #[arg(short = 'a', long)]
+        socket: Option<PathBuf>,
+    },
+
+    /// Run a command with passwords in its environment
+    Exec {
+        /// Set a variable to a password: `VAR=<name>` or
+        /// `VAR=<name>:<field>`
+        #[arg(short, long = "env", value_parser = reference::parse_env)]
+        env: Vec<(String, String)>,
+
+        /// File of `VAR=<name>[:<field>]` lines
+        #[arg(long)]
+        env_file: Option<PathBuf>,
+
+        /// Command to run, after `--`
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+
+    /// Fill in `{{ pass "<name>" }}` placeholders in a template
+    Render {
+        template: PathBuf,
+
+        /// Where to write. Defaults to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Manage the unlock agent, which caches the store key between calls
+    Agent {
+        #[command(subcommand)]
+        command: AgentCommands,
+    },
+
+    /// Wipe the store key cached by the unlock agent
+    Lock,
+
+    /// Export passwords to a file or another password manager's format
+    Export {
+        #[arg(short, long, value_enum)]
+        format: Format,
+
+        /// Only export names containing this text
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// Where to write. Defaults to stdout for single-file formats
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Encrypt the output to this GPG recipient
+        #[arg(long)]
+        encrypt_to: Option<String>,
+
+        /// Do not ask for confirmation before writing plaintext
+        #[arg(long)]
+        yes: bool,
+    },
+
+    /// Import passwords from another password manager
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+    },
+
+    /// Report weak, reused, old, short and dictionary passwords
+    Audit {
+        /// Passwords unchanged for this many days are old
+        #[arg(long, default_value_t = audit::Options::default().max_age_days)]
+        days: u64,
+
+        /// Passwords shorter than this are short
+        #[arg(long, default_value_t = audit::Options::default().min_length)]
+        min_length: usize,
+
+        /// Passwords scoring below this (0 to 4) are weak
+        #[arg(
+            long,
+            default_value_t = audit::Options::default().min_score,
+            value_parser = clap::value_parser!(u8).range(0..=4)
+        )]
+        min_score: u8,
+
+        /// Also look passwords up in a downloaded Have I Been Pwned
+        /// SHA-1 file, sorted by hash
+        #[arg(long, value_name = "FILE")]
+        breached: Option<PathBuf>,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List passwords due or overdue for rotation
+    Due {
+        /// Also list passwords due within this period, e.g. `30d`
+        #[arg(long, default_value = "7d", value_parser = parse_period)]
+        within: u64,
+    },
+
+    /// Replace a password with a generated one, keeping the old one in
+    /// its history
+    Rotate {
+        name: Option<String>,
+
+        /// Length of the new password. Defaults to `generator.length`
+        #[arg(short, long)]
+        length: Option<usize>,
+    },
+
+    /// Manage named stores
+    Stores {
+        #[command(subcommand)]
+        command: Option<StoresCommands>,
+    },
+
+    /// Read and write settings in the config file
+    Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommands>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AttachCommands {
+    /// Attach a file, replacing any attachment with the same name
+    Add {
+        name: String,
+        file: PathBuf,
+
+        /// Name to store the file under. Defaults to its file name
+        #[arg(long = "as")]
+        rename: Option<String>,
+    },
+
+    /// Write an attachment to a file readable only by you
+    Get {
+        name: String,
+        attachment: String,
+
+        /// Where to write. Defaults to the attachment's name
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// List the attachments of a password
+    Ls { name: String },
+
+    /// Remove an attachment
+    Rm { name: String, attachment: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum TagCommands {
+    /// Add tags to a password
+    Add {
+        name: String,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+
+    /// Remove tags from a password
+    Rm {
+        name: String,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+
+    /// List the tags of a password, or every tag in use
+    Ls { name: Option<String> },
+}
+
+#[derive(Subcommand, Debug)]
+enum StoresCommands {
+    /// List named stores
+    List,
+
+    /// Register a store directory under a name
+    Add { name: String, dir: PathBuf },
+
+    /// Forget a named store. Its files are left alone
+    Remove { name: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommands {
+    /// List every setting with its value and where it came from
+    List,
+
+    /// Show the value of a setting
+    Get { key: String },
+
+    /// Set a setting in the config file
+    Set { key: String, value: String },
+
+    /// Remove a setting from the config file
+    Unset { key: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum OtpCommands {
+    /// Add an otpauth:// URI to a password, creating it if needed
+    Insert { name: String, uri: Option<String> },
+
+    /// Add the accounts of a QR code in a PNG or JPEG image
+    Import {
+        image: PathBuf,
+
+        /// Name to store a single account under. Defaults to
+        /// `<issuer>/<account>`
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AgentCommands {
+    /// Start the agent in the background
+    Start {
+        /// Seconds of inactivity after which the key is wiped
+        #[arg(long, default_value_t = agent::DEFAULT_TIMEOUT)]
+        timeout: u64,
+    },
+
+    /// Show whether the agent is running
+    Status,
+
+    /// Run the agent in the foreground
+    #[command(hide = true)]
+    Serve {
+        #[arg(long, default_value_t = agent::DEFAULT_TIMEOUT)]
+        timeout: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ImportSource {
+    /// Import a zx2c4 `pass` directory of .gpg files
+    #[command(name = "password-store")]
+    PasswordStore {
+        dir: PathBuf,
+
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+
+    /// Import an unencrypted Bitwarden JSON export
+    Bitwarden {
+        file: PathBuf,
+
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+
+    /// Import a 1Password .1pux or CSV export
+    #[command(name = "1password")]
+    OnePassword {
+        file: PathBuf,
+
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+
+    /// Import a KeePassXC CSV export
+    Keepassxc {
+        file: PathBuf,
+
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+
+    /// Import a CSV file with a header row
+    Csv {
+        file: PathBuf,
+
+        /// Header to use for a column, e.g. `--map password=Secret`.
+        /// Columns: name, password, username, url, notes, folder, totp
+        #[arg(long, value_parser = parse_mapping)]
+        map: Vec<(String, String)>,
+
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+}
+
+fn parse_period(arg: &str) -> Result<u64, String> {
+    expiry::parse_period(arg).ok_or_else(|| {
+        format!("expected a period like 30d, 4w, 6m or 1y, got `{arg}`")
+    })
+}
+
+fn parse_setting(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((k, v)) => Ok((k.trim().to_string(), v.to_string())),
+        None => Err(format!("expected <key>=<value>, got `{arg}`")),
+    }
 }
 
-fn get_db() -> Option<Database> {
-    match Database::read() {
+fn parse_mapping(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((k, v)) => Ok((k.trim().to_string(), v.trim().to_string())),
+        None => Err(format!("expected <column>=<header>, got `{arg}`")),
+    }
+}
+
+#[derive(ClapArgs, Debug)]
+struct ImportOptions {
+    /// List what would be imported without changing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// What to do when a name already exists
+    #[arg(long, value_enum, default_value_t = Conflict::Skip)]
+    conflict: Conflict,
+}
+
+#[derive(Subcommand, Debug)]
+enum TrashCommands {
+    /// List removed passwords
+    List,
+
+    /// Bring back a removed password
+    Restore { name: String },
+
+    /// Permanently delete removed passwords
+    Purge { name: Option<String> },
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Settings for this run: the config file with `-c` overrides applied
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+fn load_config(args: &Args) -> crate::Result<Config> {
+    let mut config = Config::load()?;
+    for (key, value) in &args.overrides {
+        config.set_override(key, value)?;
+    }
+    if let Some(name) = &args.store {
+        config.use_store(name)?;
+    }
+    if let Some(dir) = &args.store_path {
+        config.use_store_path(&std::path::absolute(dir)?);
+    }
+    Ok(config)
+}
+
+fn get_store() -> Option<Store> {
+    match Store::open_at(Database::path_in(config())) {
         Ok(v) => Some(v),
         Err(Error::DataFileNotFound) => {
             eprintln!("Database not found. Run `pass init <gpg-id>` first.");
-            return None;
+            None
         }
         Err(e) => {
-            eprintln!("Failed to read pass.store.\nError: {e:?}");
-            return None;
+            eprintln!("Failed to read pass.store.\nError: {e}");
+            None
         }
     }
 }
@@ -69,53 +575,152 @@ fn get_db() -> Option<Database> {
 pub fn run() -> Option<()> {
     let args = Args::parse();
 
-    if let Some(name) = args.name {
-        return Some(get_password(get_db()?, &name));
+    match load_config(&args) {
+        Ok(v) => CONFIG.set(v).unwrap(),
+        Err(e) => {
+            eprintln!("{e}");
+            return None;
+        }
     }
+    agent::set_enabled(config().agent_enabled());
 
-    if let None = args.command {
-        return Some(search_password(get_db()?));
+    if let Some(name) = args.name {
+        get_password(get_store()?, &name);
+        return Some(());
     }
 
-    match args.command.unwrap() {
-        Commands::Init { gpg_id } => initialize_db(gpg_id),
-        Commands::Reinit { gpg_id } => reinitialize_db(get_db()?, gpg_id),
-        Commands::Location => {
-            println!("database is at {}", pretty_location());
+    let Some(command) = args.command else {
+        search_password(get_store()?);
+        return Some(());
+    };
+
+    match command {
+        Commands::Init { gpg_id, hide_key_id } => {
+            initialize_db(gpg_id, hide_key_id)
+        }
+        Commands::Reinit { gpg_id, hide_key_id, show_key_id } => {
+            let hide = match (hide_key_id, show_key_id) {
+                (false, false) => None,
+                (hide, _) => Some(hide),
+            };
+            reinitialize_db(get_store()?, gpg_id, hide)
+        }
+        Commands::Location { verbose } => show_location(verbose),
+        Commands::Ls { long, tag, filter } => {
+            list_passwords(get_store()?, long, tag, filter)
+        }
+        Commands::Attach { command } => {
+            manage_attachments(get_store()?, command)
         }
-        Commands::Insert { name, password } => {
-            insert_password(get_db()?, name, password)
+        Commands::Tag { command } => manage_tags(get_store()?, command),
+        Commands::Insert { name, password, generate, length } => {
+            let password = match generate {
+                true => Some(generate_password(length)),
+                false => password,
+            };
+            insert_password(get_store()?, name, password)
+        }
+        Commands::Move { current, next } => rename(get_store()?, current, next),
+        Commands::Copy {
+            names,
+            to_store,
+            force,
+            with_history,
+            with_attachments,
+        } => copy_to_store(
+            get_store()?,
+            names,
+            to_store,
+            force,
+            with_history,
+            with_attachments,
+        ),
+        Commands::Edit { name } => edit_password(get_store()?, name),
+        Commands::Remove { name } => remove_password(get_store()?, name),
+        Commands::Log { name } => show_log(get_store()?, name),
+        Commands::Show { name, version } => {
+            show_version(get_store()?, name, version)
+        }
+        Commands::Restore { name, version } => {
+            restore_version(get_store()?, name, version)
+        }
+        Commands::Trash { command } => {
+            manage_trash(get_store()?, command.unwrap_or(TrashCommands::List))
+        }
+        Commands::Otp { command: None, name } => show_otp(get_store()?, name),
+        Commands::Otp { command: Some(command), .. } => match command {
+            OtpCommands::Insert { name, uri } => {
+                insert_otp(get_store()?, name, uri)
+            }
+            OtpCommands::Import { image, name } => {
+                import_otp(get_store()?, image, name)
+            }
+        },
+        Commands::SshAdd { name, lifetime } => {
+            ssh_add(get_store()?, name, lifetime)
+        }
+        Commands::SshAgent { names, socket } => ssh_agent(names, socket),
+        Commands::Exec { env, env_file, command } => {
+            exec(get_store()?, env, env_file, command)
+        }
+        Commands::Render { template, output } => {
+            render(get_store()?, template, output)
+        }
+        Commands::Agent { command } => manage_agent(command),
+        Commands::Lock => match agent::lock() {
+            true => println!("Locked."),
+            false => println!("No agent running."),
+        },
+        Commands::Export { format, filter, output, encrypt_to, yes } => {
+            export_to(get_store()?, format, filter, output, encrypt_to, yes)
+        }
+        Commands::Import { source } => import_from(get_store()?, source),
+        Commands::Audit { days, min_length, min_score, breached, json } => {
+            let options =
+                audit::Options { min_score, min_length, max_age_days: days };
+            run_audit(get_store()?, options, breached, json)
+        }
+        Commands::Due { within } => show_due(get_store()?, within),
+        Commands::Rotate { name, length } => {
+            rotate_password(get_store()?, name, length)
+        }
+        Commands::Stores { command } => {
+            manage_stores(command.unwrap_or(StoresCommands::List))
+        }
+        Commands::Config { command } => {
+            manage_config(command.unwrap_or(ConfigCommands::List))
         }
-        Commands::Move { current, next } => rename(get_db()?, current, next),
-        Commands::Edit { name } => edit_password(get_db()?, name),
-        Commands::Remove { name } => remove_password(get_db()?, name),
     };
 
     Some(())
 }
 
-fn initialize_db(gpg_id: String) {
-    if Database::read().map_or(false, |v| v.gpg_id().is_some()) {
+fn initialize_db(gpg_id: String, hide_key_id: bool) {
+    if Store::open_at(Database::path_in(config()))
+        .is_ok_and(|v| v.db().gpg_id().is_some())
+    {
         return println!("Current database already has an owner id.");
     }
     println!("Creating new database using {gpg_id}");
-    let test = Command::new("gpg").args(["-K", &gpg_id]).output().unwrap();
-    let ok = String::from_utf8_lossy(&test.stdout).contains(&gpg_id);
-
-    if ok {
-        let _ = Database::new(Some(gpg_id)).write();
+    if Gpg::new(&gpg_id).has_secret_key() {
+        let mut store = Store::create(Database::path_in(config()), &gpg_id);
+        store.db_mut().set_hide_gpg_id(hide_key_id);
+        let _ = store.save();
     } else {
         println!("Invalid key id given. Try using `gpg -K` to show the available keys");
     }
 }
 
-fn reinitialize_db(mut db: Database, gpg_id: String) {
-    db.set_gpg_id(&gpg_id);
-    let _ = db.write();
+/// `hide` changes whether the key id is hidden; `None` keeps it as is.
+fn reinitialize_db(mut store: Store, gpg_id: String, hide: Option<bool>) {
+    store.db_mut().set_gpg_id(&gpg_id);
+    if let Some(hide) = hide {
+        store.db_mut().set_hide_gpg_id(hide);
+    }
+    let _ = store.save();
 }
 
-fn pretty_location() -> String {
-    let path = Database::path();
+fn pretty_location(path: &Path) -> String {
     let pretty = path.to_string_lossy();
     if pretty.contains(' ') {
         format!("'{pretty}'")
@@ -124,22 +729,228 @@ fn pretty_location() -> String {
     }
 }
 
-fn search_password(db: Database) {
-    let selection = match db.select_one() {
+fn show_location(verbose: bool) {
+    let (path, source) = Database::locate(config());
+    println!("database is at {}", pretty_location(&path));
+    if !verbose {
+        return;
+    }
+    let source = match source {
+        config::Source::Default => "default directory".to_string(),
+        config::Source::File => {
+            format!("store.dir in {}", Config::path().display())
+        }
+        v => v.to_string(),
+    };
+    println!("chosen by {source}");
+    if !path.is_file() {
+        println!("(no store there yet)");
+    }
+}
+
+fn select_one(store: &Store) -> Option<String> {
+    select_from(store, store.list())
+}
+
+/// Pick one of `names` with skim. Tags are shown after each name, so
+/// typing `#prod` narrows the list to entries tagged `prod`.
+fn select_from(store: &Store, names: Vec<String>) -> Option<String> {
+    let choices = names
+        .into_iter()
+        .map(|name| match entry::tags(store.db().get_unchecked(&name)) {
+            tags if tags.is_empty() => name,
+            tags => format!("{name}\t#{}", tags.join(" #")),
+        })
+        .collect();
+    let selected = skim::select_one(choices)?;
+    Some(selected.split('\t').next().unwrap_or_default().to_string())
+}
+
+fn search_password(store: Store) {
+    let selection = match select_one(&store) {
         None => return println!("Nothing selected"),
         Some(v) => v,
     };
 
     println!("[{selection}]");
-    get_password(db, &selection)
+    get_password(store, &selection)
 }
 
-fn get_password(db: Database, name: &str) {
-    let data = match db.get(name) {
-        None => return println!("No password found for [{name}]"),
-        Some(v) => v,
+fn list_passwords(
+    store: Store,
+    long: bool,
+    mut tags: Vec<String>,
+    filter: Option<String>,
+) {
+    if let Some(filter) = filter {
+        match config().filter(&filter) {
+            Ok(v) => tags.extend(v),
+            Err(e) => return eprintln!("{e}"),
+        }
+    }
+    for name in store.list_tagged(&tags) {
+        if !long {
+            println!("{name}");
+            continue;
+        }
+        let entry = store.entry(&name).unwrap();
+        let files = entry.attachments();
+        let size = files.iter().map(Attachment::size).sum();
+        let files = match files.len() {
+            0 => String::new(),
+            1 => format!("1 file, {}", attachment::format_size(size)),
+            n => format!("{n} files, {}", attachment::format_size(size)),
+        };
+        let modified = timestamp::format(entry.modified());
+        println!("{modified:<16}  {files:<20}  {name}");
+    }
+}
+
+fn manage_attachments(mut store: Store, command: AttachCommands) {
+    match command {
+        AttachCommands::Add { name, file, rename } => {
+            let total = store.db().attachments_size();
+            let Some(entry) = store.db_mut().entry_mut(&name) else {
+                return println!("No password found for [{name}]");
+            };
+            let rename = rename.or_else(|| {
+                file.file_name().map(|v| v.to_string_lossy().to_string())
+            });
+            let Some(rename) = rename.filter(|v| !v.is_empty()) else {
+                return eprintln!("Give the attachment a name with --as");
+            };
+            let data = match fs::read(&file) {
+                Ok(v) if v.len() > attachment::MAX_SIZE => {
+                    let limit =
+                        attachment::format_size(attachment::MAX_SIZE as u64);
+                    return eprintln!("Attachments are limited to {limit}");
+                }
+                Ok(v) => v,
+                Err(e) => {
+                    return eprintln!("Failed to read {}: {e}", file.display())
+                }
+            };
+            // an attachment with the same name is replaced
+            let replaced = entry.attachment(&rename).map_or(0, |v| v.size());
+            let after = total - replaced + data.len() as u64;
+            if after > attachment::MAX_TOTAL_SIZE {
+                let limit = attachment::MAX_TOTAL_SIZE;
+                let limit = attachment::format_size(limit);
+                return eprintln!("Attachments are limited to {limit} a store");
+            }
+            let size = attachment::format_size(data.len() as u64);
+            entry.attach(Attachment::new(&rename, data));
+            store.save().unwrap();
+            println!("Attached {rename} ({size}) to [{name}]");
+        }
+        AttachCommands::Get { name, attachment, output } => {
+            let Some(entry) = store.entry(&name) else {
+                return println!("No password found for [{name}]");
+            };
+            let Some(found) = entry.attachment(&attachment) else {
+                return println!("No attachment {attachment} in [{name}]");
+            };
+            // only the base name, so that a stored name cannot point
+            // outside the current directory
+            let output = output.unwrap_or_else(|| {
+                Path::new(&attachment)
+                    .file_name()
+                    .map_or_else(|| PathBuf::from("attachment"), PathBuf::from)
+            });
+            match write_private(&output, found.data()) {
+                Ok(()) => println!("Wrote {}", output.display()),
+                Err(e) => {
+                    eprintln!("Failed to write {}: {e}", output.display())
+                }
+            }
+        }
+        AttachCommands::Ls { name } => {
+            let Some(entry) = store.entry(&name) else {
+                return println!("No password found for [{name}]");
+            };
+            for v in entry.attachments() {
+                let added = timestamp::format(v.added);
+                let size = attachment::format_size(v.size());
+                println!("{added}  {size:>10}  {}", v.name);
+            }
+        }
+        AttachCommands::Rm { name, attachment } => {
+            let Some(entry) = store.db_mut().entry_mut(&name) else {
+                return println!("No password found for [{name}]");
+            };
+            if !entry.detach(&attachment) {
+                return println!("No attachment {attachment} in [{name}]");
+            }
+            store.save().unwrap();
+            println!("Removed {attachment} from [{name}]");
+        }
+    }
+}
+
+fn manage_tags(mut store: Store, command: TagCommands) {
+    let (name, add, remove) = match command {
+        TagCommands::Add { name, tags } => (name, tags, vec![]),
+        TagCommands::Rm { name, tags } => (name, vec![], tags),
+        TagCommands::Ls { name: None } => {
+            let mut counts = std::collections::BTreeMap::new();
+            for name in store.list() {
+                for tag in entry::tags(store.db().get_unchecked(&name)) {
+                    *counts.entry(tag).or_insert(0) += 1;
+                }
+            }
+            for (tag, count) in counts {
+                println!("{tag}  ({count})");
+            }
+            return;
+        }
+        TagCommands::Ls { name: Some(name) } => match store.get(&name) {
+            None => return println!("No password found for [{name}]"),
+            Some(v) => {
+                return entry::tags(v).iter().for_each(|v| println!("{v}"))
+            }
+        },
     };
 
+    let Some(value) = store.get(&name) else {
+        return println!("No password found for [{name}]");
+    };
+    let mut tags = entry::tags(value);
+    for tag in add {
+        if tag.contains([',', ' ']) {
+            return eprintln!("Tags cannot contain commas or spaces: `{tag}`");
+        }
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags.retain(|v| !remove.contains(v));
+    let value = entry::with_tags(value, &tags);
+    store.amend(&name, &value).unwrap();
+    store.save().unwrap();
+}
+
+fn get_password(store: Store, name: &str) {
+    let Some(entry) = store.entry(name) else {
+        return println!("No password found for [{name}]");
+    };
+    copy_value(entry.value());
+    match expiry::due_date(entry) {
+        Ok(Some(due)) if due <= timestamp::now() => eprintln!(
+            "Warning: [{name}] was due for rotation on {}. \
+             Run `pass rotate {name}` to replace it.",
+            timestamp::format_date(due)
+        ),
+        Ok(_) => {}
+        Err(e) => eprintln!("Warning: [{name}]: {e}"),
+    }
+}
+
+/// Print an entry's metadata and copy its password to the clipboard, or
+/// print the whole entry if `output` is `print`
+fn copy_value(data: &str) {
+    if config().print_output() {
+        return println!("{data}");
+    }
     if let Some((password, metadata)) = data.split_once('\n') {
         println!("{metadata}");
         clip::temp_write(password);
@@ -148,18 +959,18 @@ fn get_password(db: Database, name: &str) {
     }
     println!(
         "{LINE}\nCopied password to clipboard. Will reset after {} seconds.",
-        clip::RESTORE_DELAY
+        clip::restore_delay()
     )
 }
 
-fn insert_password(mut db: Database, name: String, password: Option<String>) {
-    if db.has_name(&name) {
+fn insert_password(mut store: Store, name: String, password: Option<String>) {
+    if store.has_name(&name) {
         eprintln!("Database already has an entry for [{name}]");
         return;
     }
 
     let password = match password {
-        Some(v) => v,
+        Some(v) => Zeroizing::new(v),
         None => match prompt_password_twice(&name) {
             Some(v) => v,
             None => {
@@ -168,28 +979,76 @@ fn insert_password(mut db: Database, name: String, password: Option<String>) {
         },
     };
 
-    db.insert(&name, &password);
-    db.write().unwrap();
+    store.insert(&name, &password).unwrap();
+    store.save().unwrap();
 }
 
-fn rename(mut db: Database, current: String, next: String) {
-    if let Some(password) = db.remove(&current) {
-        db.insert(&next, &password);
-        db.write().unwrap();
+fn rename(mut store: Store, current: String, next: String) {
+    if store.has_name(&next) {
+        return eprintln!("Database already has an entry for [{next}]");
     }
+    if store.db_mut().rename(&current, &next) {
+        store.save().unwrap();
+    }
+}
+
+/// Copy the current values of passwords, with their tags, to another
+/// store, where they are encrypted for that store's GPG id. History and
+/// attachments are only copied when asked for.
+fn copy_to_store(
+    store: Store,
+    names: Vec<String>,
+    target: String,
+    force: bool,
+    history: bool,
+    attachments: bool,
+) {
+    let names = match names.is_empty() {
+        true => select_one(&store).into_iter().collect(),
+        false => names,
+    };
+    let path = match config().store(&target) {
+        Ok(dir) => Database::path_at(&dir),
+        Err(e) => return eprintln!("{e}"),
+    };
+    if path == store.path() {
+        return eprintln!("[{target}] is the current store");
+    }
+    let mut other = match Store::open_at(&path) {
+        Ok(v) => v,
+        Err(e) => return eprintln!("Failed to read [{target}]: {e}"),
+    };
+
+    let mut copied = 0;
+    for name in names {
+        let Some(entry) = store.entry(&name) else {
+            eprintln!("No password found for [{name}]");
+            continue;
+        };
+        if other.has_name(&name) && !force {
+            eprintln!("[{target}] already has [{name}], skipping");
+            continue;
+        }
+        other.db_mut().put(&name, entry.copy(history, attachments));
+        copied += 1;
+    }
+    if copied > 0 {
+        other.save().unwrap();
+    }
+    println!("Copied {copied} password(s) to [{target}]");
 }
 
 /// Prompt the user twice for a password to insert
-fn prompt_password_twice(name: &str) -> Option<String> {
+fn prompt_password_twice(name: &str) -> Option<Zeroizing<String>> {
     let mut stdout = std::io::stdout();
 
     print!("Enter password for [{name}] > ");
     stdout.flush().unwrap();
-    let p1 = read_password().unwrap();
+    let p1 = Zeroizing::new(read_password().unwrap());
 
     print!("Retype password for [{name}] > ");
     stdout.flush().unwrap();
-    let p2 = read_password().unwrap();
+    let p2 = Zeroizing::new(read_password().unwrap());
 
     (p1 == p2).then_some(p1)
 }
@@ -197,13 +1056,13 @@ fn prompt_password_twice(name: &str) -> Option<String> {
 /// Use skim to select a context to edit,
 /// then open current password in a temporary $EDITOR buffer
 /// save the entire buffer as the password
-fn edit_password(mut db: Database, name: Option<String>) {
-    let name = match name.or_else(|| db.select_one()) {
+fn edit_password(mut store: Store, name: Option<String>) {
+    let name = match name.or_else(|| select_one(&store)) {
         None => return println!("No name selected to edit"),
         Some(v) => v,
     };
 
-    let old_value = match db.get(&name) {
+    let old_value = match store.get(&name) {
         None => return println!("No value found for [{name}]"),
         Some(v) => v,
     };
@@ -216,38 +1075,586 @@ fn edit_password(mut db: Database, name: Option<String>) {
     let tmp_file = get_temp_file();
     fs::write(&tmp_file, old_value.as_bytes()).unwrap();
 
-    edit_file(&editor, &tmp_file);
+    edit_file(editor, &tmp_file);
     // TODO: shred this file or encrypt it, because this seems to be a
     // weak point
 
-    let new_value = fs::read_to_string(&tmp_file).unwrap();
+    let new_value = Zeroizing::new(fs::read_to_string(&tmp_file).unwrap());
 
     let _ = fs::remove_file(&tmp_file);
 
-    if old_value == &new_value {
+    if old_value == new_value.as_str() {
         return println!("No change required.");
     }
 
-    db.update(&name, new_value.trim());
-    db.write().unwrap();
+    store.update(&name, new_value.trim()).unwrap();
+    store.save().unwrap();
 }
 
 /// Use skim to select a context to remove.
-fn remove_password(mut db: Database, name: Option<String>) {
-    let name = match name.or_else(|| db.select_one()) {
+fn remove_password(mut store: Store, name: Option<String>) {
+    let name = match name.or_else(|| select_one(&store)) {
         None => return println!("No name selected to edit"),
         Some(v) => v,
     };
-    db.remove(&name);
-    db.write().unwrap()
+    if let Err(e) = store.remove(&name) {
+        return eprintln!("{e}");
+    }
+    store.save().unwrap()
+}
+
+/// List the versions of a password without revealing them
+fn show_log(store: Store, name: Option<String>) {
+    let name = match name.or_else(|| select_one(&store)) {
+        None => return println!("No name selected"),
+        Some(v) => v,
+    };
+    let entry = match store.entry(&name) {
+        None => return println!("No password found for [{name}]"),
+        Some(v) => v,
+    };
+    println!("[{name}]");
+    for k in 0..entry.version_count() {
+        let version = entry.version(k).unwrap();
+        let current = if k == 0 { "  (current)" } else { "" };
+        println!("{k:>4}  {}{current}", timestamp::format(version.modified));
+    }
+}
+
+fn show_version(store: Store, name: Option<String>, version: usize) {
+    let name = match name.or_else(|| select_one(&store)) {
+        None => return println!("No name selected"),
+        Some(v) => v,
+    };
+    let entry = match store.entry(&name) {
+        None => return println!("No password found for [{name}]"),
+        Some(v) => v,
+    };
+    match entry.version(version) {
+        None => println!("No version {version} for [{name}]"),
+        Some(v) => copy_value(&v.value),
+    }
+}
+
+fn restore_version(mut store: Store, name: String, version: usize) {
+    if !store.db_mut().restore(&name, version) {
+        return println!("No version {version} for [{name}]");
+    }
+    store.save().unwrap();
+    println!("Restored version {version} of [{name}]");
+}
+
+fn manage_trash(mut store: Store, command: TrashCommands) {
+    match command {
+        TrashCommands::List => {
+            for trashed in store.db().trash() {
+                let removed = timestamp::format(trashed.removed);
+                println!("{removed}  {}", trashed.name);
+            }
+        }
+        TrashCommands::Restore { name } => {
+            if store.has_name(&name) {
+                return eprintln!("Database already has an entry for [{name}]");
+            }
+            if !store.db_mut().untrash(&name) {
+                return println!("No removed password found for [{name}]");
+            }
+            store.save().unwrap();
+            println!("Restored [{name}]");
+        }
+        TrashCommands::Purge { name } => {
+            let count = store.db_mut().purge(name.as_deref());
+            store.save().unwrap();
+            println!("Purged {count} removed password(s)");
+        }
+    }
+}
+
+/// Copy the current one-time code of an entry. HOTP counters are
+/// incremented and saved.
+fn show_otp(mut store: Store, name: Option<String>) {
+    let name = match name.or_else(|| {
+        let mut names = store.list();
+        names.retain(|v| otp::find(store.db().get_unchecked(v)).is_some());
+        select_from(&store, names)
+    }) {
+        None => return println!("No name selected"),
+        Some(v) => v,
+    };
+    let value = match store.get(&name) {
+        None => return println!("No password found for [{name}]"),
+        Some(v) => Zeroizing::new(v.to_string()),
+    };
+    let otp = match otp::find(&value) {
+        None => return println!("No otp settings found for [{name}]"),
+        Some(Err(e)) => return eprintln!("{e}"),
+        Some(Ok(v)) => v,
+    };
+
+    // the next counter is saved before the code is shown, so that a
+    // failed save cannot hand out the same code twice
+    if otp.kind == Kind::Hotp {
+        let uri = otp::find_uri(&value).unwrap();
+        let next = otp::with_counter(uri, otp.counter + 1);
+        let value = Zeroizing::new(value.replacen(uri, &next, 1));
+        store.amend(&name, &value).unwrap();
+        if let Err(e) = store.save() {
+            return eprintln!("Failed to save the next counter: {e}");
+        }
+    }
+
+    let now = timestamp::now();
+    if config().print_output() {
+        return println!("{}", otp.code(now));
+    }
+    clip::temp_write(&otp.code(now));
+    match otp.kind {
+        Kind::Totp => println!(
+            "{LINE}\nCopied code to clipboard. Expires in {} seconds.",
+            otp.remaining(now)
+        ),
+        Kind::Hotp => println!(
+            "{LINE}\nCopied code to clipboard. Counter is now {}.",
+            otp.counter + 1
+        ),
+    }
+}
+
+fn insert_otp(mut store: Store, name: String, uri: Option<String>) {
+    let uri = match uri {
+        Some(v) => v,
+        None => {
+            print!("Enter otpauth:// URI for [{name}] > ");
+            std::io::stdout().flush().unwrap();
+            read_password().unwrap()
+        }
+    };
+    let uri = uri.trim();
+    if let Err(e) = Otp::parse(uri) {
+        return eprintln!("{e}");
+    }
+
+    add_otp(&mut store, &name, uri).unwrap();
+    store.save().unwrap();
+    println!("Saved otp settings for [{name}]");
+}
+
+fn import_otp(mut store: Store, image: PathBuf, name: Option<String>) {
+    let accounts = match otp::read_image(&image) {
+        Ok(v) => v,
+        Err(e) => return eprintln!("{e}"),
+    };
+    if name.is_some() && accounts.len() > 1 {
+        let count = accounts.len();
+        return eprintln!("Found {count} accounts, --name needs exactly one");
+    }
+    for account in &accounts {
+        let name = name.clone().unwrap_or_else(|| account.suggested_name());
+        if name.is_empty() {
+            eprintln!("skip  account without a name (use --name)");
+            continue;
+        }
+        add_otp(&mut store, &name, &account.to_uri()).unwrap();
+        println!("add   [{name}]");
+    }
+    store.save().unwrap();
+}
+
+/// Set the otpauth:// URI of an entry, replacing any existing one.
+/// Creates an entry with an empty password if none exists.
+fn add_otp(store: &mut Store, name: &str, uri: &str) -> crate::Result<()> {
+    match store.get(name) {
+        None => store.insert(name, &format!("\notpauth: {uri}")),
+        Some(v) => {
+            let value = match otp::find_uri(v) {
+                Some(old) => v.replacen(old, uri, 1),
+                None => format!("{v}\notpauth: {uri}"),
+            };
+            store.update(name, &value)
+        }
+    }
+}
+
+/// Prompt for the passphrase of an encrypted SSH key
+fn prompt_passphrase(name: &str) -> Option<String> {
+    rpassword::prompt_password(format!("Passphrase for [{name}] > ")).ok()
+}
+
+fn ssh_add(store: Store, name: Option<String>, lifetime: Option<u32>) {
+    let name = match name.or_else(|| {
+        let mut names = store.list();
+        names.retain(|v| ssh::find_key(store.db().get_unchecked(v)).is_some());
+        select_from(&store, names)
+    }) {
+        None => return println!("No name selected"),
+        Some(v) => v,
+    };
+    let value = match store.get(&name) {
+        None => return println!("No password found for [{name}]"),
+        Some(v) => v,
+    };
+    let added = ssh::parse_key(value, || prompt_passphrase(&name))
+        .and_then(|key| ssh::add_identity(&key, lifetime));
+    match added {
+        Ok(_) => println!("Added [{name}] to ssh-agent"),
+        Err(e) => eprintln!("{e}"),
+    }
 }
 
-/// Get an installed editor
-fn get_editor() -> Option<PathBuf> {
+/// A socket path in a fresh directory only we can read
+fn default_agent_socket() -> std::io::Result<PathBuf> {
+    use std::os::unix::fs::DirBuilderExt;
+    let dir =
+        std::env::temp_dir().join(format!("pass-ssh-{}", std::process::id()));
+    fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    Ok(dir.join("agent.sock"))
+}
+
+fn ssh_agent(names: Vec<String>, socket: Option<PathBuf>) {
+    let socket = match socket.map_or_else(default_agent_socket, Ok) {
+        Ok(v) => v,
+        Err(e) => return eprintln!("Failed to create agent socket: {e}"),
+    };
+    println!("SSH_AUTH_SOCK={}; export SSH_AUTH_SOCK;", socket.display());
+
+    let result = ssh::serve(&socket, move || {
+        let store = Store::open_at(Database::path_in(config()))?;
+        let names = match names.is_empty() {
+            true => store.list(),
+            false => names.clone(),
+        };
+        let mut keys = vec![];
+        for name in names {
+            let value = store.get(&name).unwrap_or("");
+            if ssh::find_key(value).is_none() {
+                continue;
+            }
+            match ssh::parse_key(value, || prompt_passphrase(&name)) {
+                Ok(key) => keys.push(key),
+                Err(e) => eprintln!("skip  [{name}] ({e})"),
+            }
+        }
+        eprintln!("Serving {} key(s)", keys.len());
+        Ok(keys)
+    });
+    if let Err(e) = result {
+        eprintln!("{e}");
+    }
+}
+
+/// Replace this process with `command`, with the referenced passwords
+/// set in its environment only.
+fn exec(
+    store: Store,
+    mut vars: Vec<(String, String)>,
+    env_file: Option<PathBuf>,
+    command: Vec<String>,
+) {
+    use std::os::unix::process::CommandExt;
+
+    if let Some(path) = env_file {
+        let parsed = fs::read_to_string(&path)
+            .map_err(Error::from)
+            .and_then(|v| reference::parse_env_file(&v));
+        match parsed {
+            // variables given on the command line take priority
+            Ok(v) => vars = v.into_iter().chain(vars).collect(),
+            Err(e) => return eprintln!("{path:?}: {e}"),
+        }
+    }
+
+    let mut env = vec![];
+    for (var, name) in vars {
+        match reference::resolve(&store, &name) {
+            Ok(value) => env.push((var, value)),
+            Err(e) => return eprintln!("{var}: {e}"),
+        }
+    }
+    drop(store);
+
+    let error = Command::new(&command[0]).args(&command[1..]).envs(env).exec();
+    eprintln!("Failed to run {}: {error}", command[0]);
+    std::process::exit(127);
+}
+
+fn render(store: Store, template: PathBuf, output: Option<PathBuf>) {
+    let rendered = fs::read_to_string(&template)
+        .map_err(Error::from)
+        .and_then(|v| template::render(&store, &v));
+    let rendered = match rendered {
+        Ok(v) => v,
+        Err(e) => return eprintln!("{template:?}: {e}"),
+    };
+    let written = match &output {
+        Some(path) => write_private(path, rendered.as_bytes()),
+        None => std::io::stdout().write_all(rendered.as_bytes()),
+    };
+    if let Err(e) = written {
+        eprintln!("Failed to write output: {e}");
+    }
+}
+
+fn manage_agent(command: AgentCommands) {
+    use std::os::unix::process::CommandExt;
+    use std::process::Stdio;
+    use std::time::Duration;
+
+    match command {
+        AgentCommands::Start { timeout } => {
+            if agent::is_running() {
+                return println!("Agent is already running.");
+            }
+            let exe = match std::env::current_exe() {
+                Ok(v) => v,
+                Err(e) => return eprintln!("Failed to start agent: {e}"),
+            };
+            let spawned = Command::new(exe)
+                .args(["agent", "serve", "--timeout", &timeout.to_string()])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .process_group(0)
+                .spawn();
+            if let Err(e) = spawned {
+                return eprintln!("Failed to start agent: {e}");
+            }
+            for _ in 0..20 {
+                if agent::is_running() {
+                    println!("Agent started. Idle timeout: {timeout}s");
+                    if !config().agent_enabled() {
+                        println!(
+                            "Stores only use it with agent.enabled set: \
+                             pass config set agent.enabled true"
+                        );
+                    }
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            eprintln!("Agent did not start.");
+        }
+        AgentCommands::Status => match agent::is_running() {
+            true => println!("Agent is running at {:?}", agent::socket_path()),
+            false => println!("No agent running."),
+        },
+        AgentCommands::Serve { timeout } => {
+            if let Err(e) = agent::serve(Duration::from_secs(timeout)) {
+                eprintln!("{e}");
+            }
+        }
+    }
+}
+
+/// Ask the user to type `yes` before continuing.
+fn confirm(message: &str) -> bool {
+    eprint!("{message}\nType 'yes' to continue > ");
+    let mut answer = String::new();
+    let _ = std::io::stdin().read_line(&mut answer);
+    answer.trim() == "yes"
+}
+
+fn export_to(
+    store: Store,
+    format: Format,
+    filter: Option<String>,
+    output: Option<PathBuf>,
+    encrypt_to: Option<String>,
+    yes: bool,
+) {
+    let mut names = store.list();
+    names.retain(|v| filter.as_ref().is_none_or(|f| v.contains(f.as_str())));
+    let items: Vec<_> = names
+        .iter()
+        .map(|name| Item { name, value: store.db().get_unchecked(name) })
+        .collect();
+
+    if !format.is_file() {
+        let Some(dir) = output else {
+            return eprintln!("An output directory is required (--output)");
+        };
+        let gpg_id = match encrypt_to.as_ref().or(store.db().gpg_id()) {
+            None => return eprintln!("No GPG id to encrypt to"),
+            Some(v) => v,
+        };
+        return match export::password_store(&items, &dir, gpg_id) {
+            Ok(skipped) => {
+                for name in &skipped {
+                    println!("skip  [{name}] (not a valid path)");
+                }
+                let count = items.len() - skipped.len();
+                println!("Exported {count} password(s) to {dir:?}");
+            }
+            Err(e) => eprintln!("Export failed: {e}"),
+        };
+    }
+
+    let destination = match &output {
+        Some(v) => format!("{v:?}"),
+        None => "stdout".to_string(),
+    };
+    if encrypt_to.is_none() && !yes {
+        let message = format!(
+            "WARNING: this writes {} password(s) in PLAINTEXT to {destination}.",
+            items.len()
+        );
+        if !confirm(&message) {
+            return eprintln!("Export cancelled.");
+        }
+    }
+
+    let data = match format {
+        Format::Json => export::json(&items),
+        Format::Csv => export::csv(&items),
+        Format::Bitwarden => export::bitwarden(&items),
+        Format::PasswordStore => unreachable!(),
+    };
+    let data = match (data, &encrypt_to) {
+        (Ok(v), Some(recipient)) => Gpg::new(recipient)
+            .encrypt(&*v)
+            .map(Zeroizing::new)
+            .map_err(Error::from),
+        (data, _) => data,
+    };
+    let data = match data {
+        Ok(v) => v,
+        Err(e) => return eprintln!("Export failed: {e}"),
+    };
+    let written = match &output {
+        Some(path) => write_private(path, &data),
+        None => std::io::stdout().write_all(&data),
+    };
+    match written {
+        Ok(_) => {
+            eprintln!("Exported {} password(s) to {destination}", items.len())
+        }
+        Err(e) => eprintln!("Export failed: {e}"),
+    }
+}
+
+fn import_from(store: Store, source: ImportSource) {
+    let (parsed, options) = match source {
+        ImportSource::PasswordStore { dir, options } => {
+            (read_password_store(&store, &dir, options.dry_run), options)
+        }
+        ImportSource::Bitwarden { file, options } => {
+            (import::bitwarden(&file), options)
+        }
+        ImportSource::OnePassword { file, options } => {
+            (import::onepassword(&file), options)
+        }
+        ImportSource::Keepassxc { file, options } => {
+            (import::csv(&file, Preset::Keepassxc, &[]), options)
+        }
+        ImportSource::Csv { file, map, options } => {
+            (import::csv(&file, Preset::Generic, &map), options)
+        }
+    };
+    match parsed {
+        Ok(parsed) => import_records(store, parsed, options),
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
+/// Decrypt every entry of a password-store tree.
+/// On a dry run nothing is decrypted and values are left empty.
+fn read_password_store(
+    store: &Store,
+    dir: &Path,
+    dry_run: bool,
+) -> crate::Result<Parsed> {
+    let gpg = Gpg::new(store.db().gpg_id().map_or("", |v| v.as_str()));
+    let mut parsed = Parsed::default();
+    for (name, path) in import::scan_password_store(dir)? {
+        if dry_run {
+            parsed.push(Record::new("", &name, ""));
+            continue;
+        }
+        match fs::read(&path).and_then(|v| gpg.decrypt(v)) {
+            Ok(v) => {
+                let value = String::from_utf8_lossy(&v);
+                parsed.push(Record::new("", &name, value.trim_end()));
+            }
+            Err(e) => parsed.skip(&name, &e.to_string()),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Insert imported records, resolving name conflicts, then report what
+/// was imported and skipped. On a dry run nothing is written.
+fn import_records(mut store: Store, parsed: Parsed, options: ImportOptions) {
+    let mut skipped = parsed.skipped;
+    let mut imported = 0;
+
+    for record in parsed.records {
+        let name = record.name;
+        let result = match import::resolve(store.db(), &name, options.conflict)
+        {
+            Action::Skip => {
+                skipped.push((name, "already exists".to_string()));
+                continue;
+            }
+            Action::Overwrite(v) => {
+                println!("over  [{name}]");
+                store.update(&v, &record.value)
+            }
+            Action::Insert(v) => {
+                match v == name {
+                    true => println!("add   [{name}]"),
+                    false => println!("add   [{name}] as [{v}]"),
+                }
+                store.insert(&v, &record.value)
+            }
+        };
+        result.unwrap();
+        imported += 1;
+    }
+    for (name, reason) in &skipped {
+        println!("skip  [{name}] ({reason})");
+    }
+
+    let skipped = skipped.len();
+    if options.dry_run {
+        return println!(
+            "{LINE}\nDry run: {imported} to import, {skipped} to skip"
+        );
+    }
+    store.save().unwrap();
+    println!("{LINE}\nImported {imported}, skipped {skipped}");
+}
+
+/// Write a file readable only by its owner. The mode given to `open`
+/// only applies to new files, so an existing file is restricted before
+/// it is emptied and written.
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .mode(0o600)
+        .open(path)?;
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.set_len(0)?;
+    file.write_all(data)
+}
+
+/// Get an installed editor, with any arguments set in the config
+fn get_editor() -> Option<Command> {
+    use which::which;
+    if let Some(words) = config().editor() {
+        let mut cmd = Command::new(which(&words[0]).ok()?);
+        cmd.args(&words[1..]);
+        return Some(cmd);
+    }
+    find_editor().map(Command::new)
+}
+
+fn find_editor() -> Option<PathBuf> {
     use which::which;
     if let Ok(v) = std::env::var("EDITOR") {
         if let Ok(v) = which(v) {
-            return Some(PathBuf::from(v));
+            return Some(v);
         }
     }
     if let Ok(v) = which("nvim") {
@@ -262,13 +1669,27 @@ fn get_editor() -> Option<PathBuf> {
     None
 }
 
-fn edit_file(editor: &PathBuf, filepath: &PathBuf) {
-    let mut cmd = Command::new(&editor);
-    cmd.arg(&filepath);
+fn edit_file(mut cmd: Command, filepath: &PathBuf) {
+    cmd.arg(filepath);
     let child = cmd.spawn().unwrap();
     let _ = child.wait_with_output();
 }
 
+/// A random password, sized and drawn as set by the `generator` settings
+fn generate_password(length: Option<usize>) -> String {
+    const SYMBOLS: &[u8] = b"!#$%&()*+,-./:;<=>?@[]^_{|}~";
+    let length = length.unwrap_or_else(|| config().generator_length());
+    let mut charset: Vec<u8> =
+        (b'a'..=b'z').chain(b'A'..=b'Z').chain(b'0'..=b'9').collect();
+    if config().generator_symbols() {
+        charset.extend_from_slice(SYMBOLS);
+    }
+    let mut rng = rand::thread_rng();
+    (0..length)
+        .map(|_| charset[rng.gen_range(0..charset.len())] as char)
+        .collect()
+}
+
 /// Get a random filename for temporary buffer
 fn get_temp_file() -> PathBuf {
     let random: String = rand::thread_rng()
@@ -276,5 +1697,186 @@ fn get_temp_file() -> PathBuf {
         .take(32)
         .map(char::from)
         .collect();
-    std::env::temp_dir().join(&format!("pass.{random}"))
+    std::env::temp_dir().join(format!("pass.{random}"))
+}
+
+fn run_audit(
+    store: Store,
+    options: audit::Options,
+    breached: Option<PathBuf>,
+    json: bool,
+) {
+    let mut report = audit::audit(store.db(), &options, timestamp::now());
+    if let Some(path) = breached {
+        let found = HashFile::open(&path)
+            .map_err(Error::from)
+            .and_then(|mut file| audit::breached(store.db(), &mut file));
+        match found {
+            Ok(v) => report.breached = Some(v),
+            Err(e) => {
+                return eprintln!("Failed to read {}: {e}", path.display())
+            }
+        }
+    }
+    if json {
+        return println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    }
+
+    println!("Checked {} password(s)", report.checked);
+    if !report.weak.is_empty() {
+        println!("{LINE}\nWeak (score below {}/4)", options.min_score);
+        for v in &report.weak {
+            let warning = v.warning.as_deref().unwrap_or("");
+            println!("  {}  score {}/4  {warning}", v.name, v.score);
+        }
+    }
+    if !report.reused.is_empty() {
+        println!("{LINE}\nReused");
+        for group in &report.reused {
+            println!("  {}", group.join(", "));
+        }
+    }
+    if !report.old.is_empty() {
+        println!("{LINE}\nNot changed in {} days", options.max_age_days);
+        for v in &report.old {
+            match v.days {
+                Some(days) => println!("  {}  {days} days", v.name),
+                None => println!("  {}  never recorded", v.name),
+            }
+        }
+    }
+    if !report.short.is_empty() {
+        println!("{LINE}\nShorter than {}", options.min_length);
+        for v in &report.short {
+            println!("  {}  {} characters", v.name, v.length);
+        }
+    }
+    if !report.dictionary.is_empty() {
+        println!("{LINE}\nDictionary words only");
+        for name in &report.dictionary {
+            println!("  {name}");
+        }
+    }
+    if let Some(breached) = report.breached.as_ref().filter(|v| !v.is_empty()) {
+        println!("{LINE}\nFound in breaches");
+        for v in breached {
+            println!("  {}  seen {} times", v.name, v.count);
+        }
+    }
+    if report.is_clean() {
+        println!("No problems found.");
+    }
+}
+
+fn show_due(store: Store, within: u64) {
+    let now = timestamp::now();
+    let (due, errors) = expiry::due(store.db(), now + within);
+    for (name, e) in errors {
+        eprintln!("[{name}]: {e}");
+    }
+    if due.is_empty() {
+        return println!("Nothing due for rotation.");
+    }
+    for v in due {
+        let date = timestamp::format_date(v.due);
+        let status = match v.due <= now {
+            true => format!("overdue by {} days", (now - v.due) / expiry::DAY),
+            false => format!("due in {} days", (v.due - now) / expiry::DAY),
+        };
+        println!("{date}  {status:<18}  {}", v.name);
+    }
+}
+
+/// Generate a new password for an entry, keeping its fields. The old
+/// password stays in the entry's history.
+fn rotate_password(
+    mut store: Store,
+    name: Option<String>,
+    length: Option<usize>,
+) {
+    let name = match name.or_else(|| select_one(&store)) {
+        None => return println!("No name selected"),
+        Some(v) => v,
+    };
+    let Some(value) = store.get(&name) else {
+        return println!("No password found for [{name}]");
+    };
+    let password = Zeroizing::new(generate_password(length));
+    let value =
+        Zeroizing::new(expiry::rotated(value, &password, timestamp::now()));
+    store.update(&name, &value).unwrap();
+    store.save().unwrap();
+    println!("Rotated [{name}]. The old password is kept as version 1.");
+    if store
+        .entry(&name)
+        .and_then(|v| expiry::due_date(v).ok().flatten())
+        .is_some_and(|v| v <= timestamp::now())
+    {
+        println!("Its `expires` date has passed; edit it to set a new one.");
+    }
+}
+
+fn manage_stores(command: StoresCommands) {
+    let result = match command {
+        StoresCommands::List => {
+            let current = Database::path_in(config());
+            let stores = config().stores();
+            let width = stores.iter().map(|v| v.0.len()).max().unwrap_or(0);
+            for (name, dir) in stores {
+                let mark = match Database::path_at(&dir) == current {
+                    true => "*",
+                    false => " ",
+                };
+                println!("{mark} {name:<width$}  {}", dir.display());
+            }
+            Ok(())
+        }
+        StoresCommands::Add { name, dir } => {
+            std::path::absolute(dir).map_err(Error::from).and_then(|dir| {
+                let mut file = Config::load()?;
+                file.add_store(&name, &dir)?;
+                file.save()
+            })
+        }
+        StoresCommands::Remove { name } => {
+            Config::load().and_then(|mut file| match file.remove_store(&name) {
+                true => file.save(),
+                false => Err(Error::StoreNotFound(name)),
+            })
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("{e}");
+    }
+}
+
+fn manage_config(command: ConfigCommands) {
+    let result = match command {
+        ConfigCommands::List => {
+            for key in config::KEYS {
+                println!("# {}", key.help);
+                match config().get(key.name) {
+                    Ok(Some((value, source))) => {
+                        println!("{} = {value}  ({source})", key.name)
+                    }
+                    Ok(None) => println!("{} is unset", key.name),
+                    Err(e) => eprintln!("{e}"),
+                }
+            }
+            Ok(())
+        }
+        ConfigCommands::Get { key } => config().get(&key).map(|v| match v {
+            Some((value, _)) => println!("{value}"),
+            None => println!("{key} is unset"),
+        }),
+        ConfigCommands::Set { key, value } => Config::load()
+            .and_then(|mut file| file.set(&key, &value).and(Ok(file)))
+            .and_then(|file| file.save()),
+        ConfigCommands::Unset { key } => Config::load()
+            .and_then(|mut file| file.unset(&key).and(Ok(file)))
+            .and_then(|file| file.save()),
+    };
+    if let Err(e) = result {
+        eprintln!("{e}");
+    }
 }
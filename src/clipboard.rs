@@ -1,9 +1,35 @@
 pub mod clip {
+    use crate::cli::config;
     use std::process::Command;
     use std::process::Output;
     use std::process::Stdio;
 
-    pub const RESTORE_DELAY: usize = 45;
+    /// Seconds before the clipboard is restored
+    pub fn restore_delay() -> u32 {
+        config().clipboard_timeout()
+    }
+
+    /// Shell commands to copy from stdin and paste to stdout
+    fn commands() -> (&'static str, &'static str) {
+        let backend = config().clipboard_backend();
+        let backend = match backend.as_str() {
+            "auto" if cfg!(target_os = "macos") => "pbcopy",
+            "auto" if std::env::var_os("WAYLAND_DISPLAY").is_some() => {
+                "wl-copy"
+            }
+            "auto" if which::which("xclip").is_err() => "xsel",
+            "auto" => "xclip",
+            v => v,
+        };
+        match backend {
+            "wl-copy" => ("wl-copy", "wl-paste -n"),
+            "xclip" => {
+                ("xclip -selection clipboard", "xclip -selection clipboard -o")
+            }
+            "xsel" => ("xsel -b -i", "xsel -b -o"),
+            _ => ("pbcopy", "pbpaste"),
+        }
+    }
 
     // run a shell command synchronously and get the output
     fn run_shell(command: &str) -> Output {
@@ -18,11 +44,11 @@ pub mod clip {
     const PID: &str = "password store sleep";
     pub fn read() -> String {
         run_shell(&format!("pkill -f \"^{PID}\" && sleep 0.5"));
-        String::from_utf8_lossy(&run_shell("pbpaste").stdout).to_string()
+        String::from_utf8_lossy(&run_shell(commands().1).stdout).to_string()
     }
 
     pub fn write(contents: &str) {
-        run_shell(&format!("printf \"{contents}\" | pbcopy"));
+        run_shell(&format!("printf \"{contents}\" | {}", commands().0));
     }
 
     pub fn temp_write(contents: &str) {
@@ -31,8 +57,9 @@ pub mod clip {
     }
 
     pub fn restore(restore: &str) {
+        let (copy, delay) = (commands().0, restore_delay());
         run_shell(&format!(
-            "((exec -a \"{PID}\" sleep {RESTORE_DELAY}); printf \"{restore}\" | pbcopy) >/dev/null 2>&1 &"
+            "((exec -a \"{PID}\" sleep {delay}); printf \"{restore}\" | {copy}) >/dev/null 2>&1 &"
         ));
     }
 }
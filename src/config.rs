@@ -0,0 +1,474 @@
+//! Per-user settings, read from a TOML file in the config directory.
+//!
+//! Each setting is resolved in order from a command line override, its
+//! environment variable (`PASS_` followed by the key in upper case, e.g.
+//! `PASS_CLIPBOARD_TIMEOUT`), the config file, then its default.
+//!
+//! ```toml
+//! editor = "code --wait"
+//! output = "print"
+//!
+//! [clipboard]
+//! timeout = 30
+//!
+//! # named stores, used with `--store <name>`
+//! [stores]
+//! team = "/home/me/team-pass"
+//!
+//! # tags to filter by, used with `pass ls --filter <name>`
+//! [filters]
+//! oncall = ["prod", "shared-with-oncall"]
+//! ```
+
+use crate::database::create_private_dir;
+use crate::{Error, Result};
+
+use std::path::{Path, PathBuf};
+use std::{env, fmt, fs};
+use toml::{Table, Value};
+
+/// Every known setting, with its type and default.
+pub const KEYS: &[Key] = &[
+    Key {
+        name: "store.dir",
+        kind: Kind::Str,
+        default: None,
+        help: "Directory holding pass.store",
+    },
+    Key {
+        name: "clipboard.timeout",
+        kind: Kind::Int,
+        default: Some("45"),
+        help: "Seconds before the clipboard is restored",
+    },
+    Key {
+        name: "clipboard.backend",
+        kind: Kind::Choice(&["auto", "pbcopy", "wl-copy", "xclip", "xsel"]),
+        default: Some("auto"),
+        help: "Program used to copy and paste",
+    },
+    Key {
+        name: "editor",
+        kind: Kind::Str,
+        default: None,
+        help: "Editor command. Defaults to $EDITOR, nvim, vim or nano",
+    },
+    Key {
+        name: "picker.height",
+        kind: Kind::Str,
+        default: Some("7"),
+        help: "Height of the fuzzy picker, in lines or a percentage",
+    },
+    Key {
+        name: "picker.reverse",
+        kind: Kind::Bool,
+        default: Some("true"),
+        help: "Show the picker prompt at the top",
+    },
+    Key {
+        name: "generator.length",
+        kind: Kind::Int,
+        default: Some("24"),
+        help: "Length of generated passwords",
+    },
+    Key {
+        name: "generator.symbols",
+        kind: Kind::Bool,
+        default: Some("false"),
+        help: "Include symbols in generated passwords",
+    },
+    Key {
+        name: "agent.enabled",
+        kind: Kind::Bool,
+        default: Some("false"),
+        help: "Keep store keys in the unlock agent between commands",
+    },
+    Key {
+        name: "output",
+        kind: Kind::Choice(&["clip", "print"]),
+        default: Some("clip"),
+        help: "Copy passwords to the clipboard or print them",
+    },
+];
+
+pub struct Key {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub default: Option<&'static str>,
+    pub help: &'static str,
+}
+
+pub enum Kind {
+    Str,
+    Int,
+    Bool,
+    Choice(&'static [&'static str]),
+}
+
+impl Key {
+    /// Environment variable overriding this key
+    pub fn env(&self) -> String {
+        format!("PASS_{}", self.name.replace('.', "_").to_uppercase())
+    }
+
+    /// Check `value` and convert it to the TOML type of this key.
+    fn parse(&self, value: &str) -> Result<Value> {
+        let invalid = |expected: &str| {
+            Error::InvalidConfig(format!(
+                "`{}` expects {expected}, got `{value}`",
+                self.name
+            ))
+        };
+        match self.kind {
+            Kind::Str => Ok(Value::String(value.to_string())),
+            Kind::Int => match value.parse::<u32>() {
+                Ok(v) => Ok(Value::Integer(v.into())),
+                Err(_) => Err(invalid("a number")),
+            },
+            Kind::Bool => match value.parse::<bool>() {
+                Ok(v) => Ok(Value::Boolean(v)),
+                Err(_) => Err(invalid("true or false")),
+            },
+            Kind::Choice(choices) => match choices.contains(&value) {
+                true => Ok(Value::String(value.to_string())),
+                false => {
+                    Err(invalid(&format!("one of {}", choices.join(", "))))
+                }
+            },
+        }
+    }
+}
+
+/// Where a setting's value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Set on the command line, by the named flag
+    Flag(String),
+    Env(String),
+    File,
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Flag(v) => write!(f, "flag {v}"),
+            Source::Env(v) => write!(f, "env {v}"),
+            Source::File => write!(f, "file"),
+            Source::Default => write!(f, "default"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    file: Table,
+    /// Key, value and the flag that set them
+    overrides: Vec<(String, String, String)>,
+}
+
+/// Stores made before `XDG_CONFIG_HOME` was followed live in the
+/// platform's directory (`~/Library/Application Support` on macOS). That
+/// one is kept as long as it exists and the XDG one does not.
+fn pick_default_dir(xdg: Option<PathBuf>, platform: PathBuf) -> PathBuf {
+    let platform = platform.join("pass");
+    match xdg.map(|v| v.join("pass")) {
+        Some(xdg) if xdg.exists() || !platform.is_dir() => xdg,
+        _ => platform,
+    }
+}
+
+impl Config {
+    /// Path of the config file: `$PASS_CONFIG`, or `config.toml` in the
+    /// config directory.
+    pub fn path() -> PathBuf {
+        match env::var_os("PASS_CONFIG") {
+            Some(v) if !v.is_empty() => PathBuf::from(v),
+            _ => Self::default_dir().join("config.toml"),
+        }
+    }
+
+    /// `pass` in `$XDG_CONFIG_HOME`, or in the platform's config
+    /// directory if that is unset.
+    pub(crate) fn default_dir() -> PathBuf {
+        let xdg = env::var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty());
+        let platform = dirs::config_dir().unwrap_or_default();
+        pick_default_dir(xdg.map(PathBuf::from), platform)
+    }
+
+    /// Read the config file. A missing file is an empty config.
+    pub fn load() -> Result<Self> {
+        let text = match fs::read_to_string(Self::path()) {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let file = text.parse::<Table>().map_err(|e| {
+            Error::InvalidConfig(format!("{}: {e}", Self::path().display()))
+        })?;
+        Ok(Self { file, overrides: vec![] })
+    }
+
+    /// Write the config file.
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            create_private_dir(dir)?;
+        }
+        let text = toml::to_string(&self.file)
+            .map_err(|e| Error::InvalidConfig(e.to_string()))?;
+        Ok(fs::write(path, text)?)
+    }
+
+    /// Override a key for this process only, taking precedence over the
+    /// environment and the file.
+    pub fn set_override(&mut self, key: &str, value: &str) -> Result<()> {
+        find(key)?.parse(value)?;
+        self.push_override(key, value, &format!("-c {key}"));
+        Ok(())
+    }
+
+    /// The value of `key` and where it came from. `None` if it is unset
+    /// and has no default.
+    pub fn get(&self, key: &str) -> Result<Option<(String, Source)>> {
+        let key = find(key)?;
+        let found = self.overrides.iter().rev().find(|v| v.0 == key.name);
+        if let Some((_, v, flag)) = found {
+            return Ok(Some((v.clone(), Source::Flag(flag.clone()))));
+        }
+        let mut vars = vec![key.env()];
+        if key.name == "store.dir" {
+            vars.push("PASSWORD_STORE_DIR".to_string());
+        }
+        for var in vars {
+            if let Ok(v) = env::var(&var) {
+                key.parse(&v)?;
+                return Ok(Some((v, Source::Env(var))));
+            }
+        }
+        if let Some(v) = self.lookup(key.name) {
+            let v = match v {
+                Value::String(v) => v.clone(),
+                v => v.to_string(),
+            };
+            key.parse(&v)?;
+            return Ok(Some((v, Source::File)));
+        }
+        Ok(key.default.map(|v| (v.to_string(), Source::Default)))
+    }
+
+    /// Set `key` in the file. Call `save` to write it.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let key = find(key)?;
+        let value = key.parse(value)?;
+        let (table, name) = match key.name.split_once('.') {
+            None => (&mut self.file, key.name),
+            Some((section, name)) => (self.section_mut(section)?, name),
+        };
+        table.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// A table of the file, created if missing
+    fn section_mut(&mut self, section: &str) -> Result<&mut Table> {
+        let table = self
+            .file
+            .entry(section)
+            .or_insert_with(|| Value::Table(Table::new()));
+        match table {
+            Value::Table(v) => Ok(v),
+            _ => {
+                Err(Error::InvalidConfig(format!("`{section}` is not a table")))
+            }
+        }
+    }
+
+    /// Remove `key` from the file. Call `save` to write it.
+    pub fn unset(&mut self, key: &str) -> Result<()> {
+        let key = find(key)?;
+        match key.name.split_once('.') {
+            None => self.file.remove(key.name),
+            Some((section, name)) => match self.file.get_mut(section) {
+                Some(Value::Table(v)) => v.remove(name),
+                _ => None,
+            },
+        };
+        Ok(())
+    }
+
+    /// Named stores registered in the `[stores]` table, with their
+    /// directories, sorted by name.
+    pub fn stores(&self) -> Vec<(String, PathBuf)> {
+        let Some(Value::Table(table)) = self.file.get("stores") else {
+            return vec![];
+        };
+        let mut stores: Vec<_> = table
+            .iter()
+            .filter_map(|(k, v)| Some((k.clone(), PathBuf::from(v.as_str()?))))
+            .collect();
+        stores.sort();
+        stores
+    }
+
+    /// Directory of the named store
+    pub fn store(&self, name: &str) -> Result<PathBuf> {
+        self.stores()
+            .into_iter()
+            .find(|v| v.0 == name)
+            .map(|v| v.1)
+            .ok_or_else(|| Error::StoreNotFound(name.to_string()))
+    }
+
+    /// Register a named store. Call `save` to write it.
+    pub fn add_store(&mut self, name: &str, dir: &Path) -> Result<()> {
+        let dir = dir.to_str().ok_or_else(|| {
+            Error::InvalidConfig(format!("{} is not UTF-8", dir.display()))
+        })?;
+        let stores = self.section_mut("stores")?;
+        stores.insert(name.to_string(), Value::String(dir.to_string()));
+        Ok(())
+    }
+
+    /// Forget a named store, leaving its files alone. Returns `false` if
+    /// there was no such store. Call `save` to write it.
+    pub fn remove_store(&mut self, name: &str) -> bool {
+        match self.file.get_mut("stores") {
+            Some(Value::Table(v)) => v.remove(name).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Tags of a saved filter from the `[filters]` table, e.g.
+    /// `oncall = ["prod", "shared-with-oncall"]`
+    pub fn filter(&self, name: &str) -> Result<Vec<String>> {
+        let unknown =
+            || Error::InvalidConfig(format!("no filter named `{name}`"));
+        let tags = self.file.get("filters").and_then(|v| v.get(name));
+        match tags.ok_or_else(unknown)? {
+            Value::Array(tags) => Ok(tags
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()),
+            Value::String(tags) => {
+                Ok(tags.split(',').map(|v| v.trim().to_string()).collect())
+            }
+            _ => Err(unknown()),
+        }
+    }
+
+    /// Use the named store for this process, as if `store.dir` was
+    /// overridden with its directory.
+    pub fn use_store(&mut self, name: &str) -> Result<()> {
+        let dir = self.store(name)?;
+        let flag = format!("--store {name}");
+        self.push_override("store.dir", &dir.to_string_lossy(), &flag);
+        Ok(())
+    }
+
+    /// Use the store in `dir` for this process.
+    pub fn use_store_path(&mut self, dir: &Path) {
+        let dir = dir.to_string_lossy();
+        self.push_override("store.dir", &dir, "--store-path");
+    }
+
+    fn push_override(&mut self, key: &str, value: &str, flag: &str) {
+        let entry = (key.to_string(), value.to_string(), flag.to_string());
+        self.overrides.push(entry);
+    }
+
+    fn lookup(&self, key: &str) -> Option<&Value> {
+        match key.split_once('.') {
+            None => self.file.get(key),
+            Some((section, name)) => self.file.get(section)?.get(name),
+        }
+    }
+
+    fn value(&self, key: &str) -> Option<String> {
+        // keys are checked on the way in, so only a bad file or
+        // environment can fail here; fall back to the default
+        match self.get(key) {
+            Ok(v) => v.map(|v| v.0),
+            Err(_) => find(key).ok()?.default.map(str::to_string),
+        }
+    }
+
+    fn number(&self, key: &str) -> u32 {
+        self.value(key).and_then(|v| v.parse().ok()).unwrap_or_default()
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.value(key).is_some_and(|v| v == "true")
+    }
+
+    pub fn clipboard_timeout(&self) -> u32 {
+        self.number("clipboard.timeout")
+    }
+
+    pub fn clipboard_backend(&self) -> String {
+        self.value("clipboard.backend").unwrap_or_default()
+    }
+
+    /// Editor command, split into the program and its arguments
+    pub fn editor(&self) -> Option<Vec<String>> {
+        let editor = self.value("editor")?;
+        let words = editor.split_whitespace().map(str::to_string).collect();
+        Some(words).filter(|v: &Vec<_>| !v.is_empty())
+    }
+
+    pub fn picker_height(&self) -> String {
+        self.value("picker.height").unwrap_or_default()
+    }
+
+    pub fn agent_enabled(&self) -> bool {
+        self.flag("agent.enabled")
+    }
+
+    pub fn picker_reverse(&self) -> bool {
+        self.flag("picker.reverse")
+    }
+
+    pub fn generator_length(&self) -> usize {
+        self.number("generator.length") as usize
+    }
+
+    pub fn generator_symbols(&self) -> bool {
+        self.flag("generator.symbols")
+    }
+
+    /// Whether passwords are printed instead of copied
+    pub fn print_output(&self) -> bool {
+        self.value("output").is_some_and(|v| v == "print")
+    }
+}
+
+fn find(key: &str) -> Result<&'static Key> {
+    KEYS.iter()
+        .find(|v| v.name == key)
+        .ok_or_else(|| Error::InvalidConfig(format!("unknown key `{key}`")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_platform_dir_while_in_use() {
+        let home = tempfile::tempdir().unwrap();
+        let xdg = home.path().join("xdg");
+        let platform = home.path().join("platform");
+        let pick = || pick_default_dir(Some(xdg.clone()), platform.clone());
+
+        assert_eq!(pick(), xdg.join("pass"));
+        assert_eq!(
+            pick_default_dir(None, platform.clone()),
+            platform.join("pass")
+        );
+
+        fs::create_dir_all(platform.join("pass")).unwrap();
+        assert_eq!(pick(), platform.join("pass"));
+        fs::create_dir_all(xdg.join("pass")).unwrap();
+        assert_eq!(pick(), xdg.join("pass"));
+    }
+}
@@ -1,29 +1,68 @@
+use crate::agent;
+use crate::attachment;
 use crate::chacha_io::{ChaReader, ChaWriter};
+use crate::config::Source;
+use crate::entry::{Entry, Trashed};
 use crate::error::Error;
 use crate::gpg::Gpg;
-use crate::sized_io::{SizedRead, SizedWrite};
-use crate::skim;
-use crate::{Header, Result};
+use crate::memory::Locked;
+use crate::sized_io::{SizedRead, SizedWrite, MAX_FRAME};
+use crate::timestamp;
+use crate::{Config, Header, Result};
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::Read;
-use std::path::PathBuf;
-use std::{env, io};
+use std::io;
+use std::io::{Read, Seek, Write};
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Marks a store written in format version 2 or later. Version 1 stores
+/// have no marker and start with a two byte frame.
+const MAGIC: &[u8; 4] = b"pass";
+
+/// Format version written by this build. Version 2 frames sections with
+/// four byte lengths instead of two.
+pub const FORMAT_VERSION: u8 = 2;
+
+/// The unencrypted start of a store file
+#[derive(Debug)]
+pub struct Preamble {
+    pub version: u8,
+    /// GPG key id the header is encrypted for. Empty if it is hidden
+    pub gpg_id: String,
+    /// The store's `Header`, encrypted with GPG
+    pub header: Vec<u8>,
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Database {
-    #[serde(skip)]
+    /// Also kept in the body, for stores whose preamble leaves it out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     gpg_id: Option<String>,
 
-    pairs: HashMap<String, String>,
+    /// Whether the key id is left out of the preamble and the encrypted
+    /// header
+    #[serde(skip)]
+    hide_gpg_id: bool,
+
+    pairs: HashMap<String, Entry>,
+
+    #[serde(default)]
+    trash: Vec<Trashed>,
 }
 
 #[allow(unused)]
 impl Database {
     pub fn new(gpg_id: Option<String>) -> Self {
-        Self { gpg_id, pairs: HashMap::new() }
+        Self {
+            gpg_id,
+            hide_gpg_id: false,
+            pairs: HashMap::new(),
+            trash: vec![],
+        }
     }
 
     pub fn gpg_id(&self) -> Option<&String> {
@@ -34,6 +73,16 @@ impl Database {
         self.gpg_id = Some(gpg_id.to_string())
     }
 
+    pub fn hides_gpg_id(&self) -> bool {
+        self.hide_gpg_id
+    }
+
+    /// Leave the key id out of the store file, so that it does not show
+    /// whose store it is. Reading it then tries every secret key.
+    pub fn set_hide_gpg_id(&mut self, hide: bool) {
+        self.hide_gpg_id = hide
+    }
+
     pub fn has_name(&self, key: &str) -> bool {
         self.pairs.contains_key(key)
     }
@@ -46,118 +95,286 @@ impl Database {
         self.pairs.iter().map(|v| v.0.to_string()).collect()
     }
 
-    pub fn select_one(&self) -> Option<String> {
-        skim::select_one(self.pairs.iter().map(|v| v.0.to_string()).collect())
-    }
-
     pub fn insert(&mut self, key: &str, value: &str) {
-        self.pairs.insert(key.to_string(), value.to_string());
+        self.pairs.insert(key.to_string(), Entry::new(value));
     }
 
     pub fn get(&self, key: &str) -> Option<&String> {
-        self.pairs.get(key)
+        self.pairs.get(key).map(|v| v.value())
     }
 
     pub fn get_unchecked(&self, key: &str) -> &String {
         self.get(key).unwrap()
     }
 
+    pub fn entry(&self, key: &str) -> Option<&Entry> {
+        self.pairs.get(key)
+    }
+
+    pub fn entry_mut(&mut self, key: &str) -> Option<&mut Entry> {
+        self.pairs.get_mut(key)
+    }
+
+    /// Update the value of an entry, keeping the previous value in its
+    /// history. Inserts a new entry if none exists.
     pub fn update(&mut self, key: &str, value: &str) {
-        self.pairs.insert(key.to_string(), value.to_string());
+        match self.pairs.get_mut(key) {
+            Some(entry) => entry.update(value),
+            None => self.insert(key, value),
+        }
     }
 
-    pub fn remove(&mut self, key: &str) -> Option<String> {
-        self.pairs.remove(key)
+    /// Replace the value of an entry without versioning it or touching
+    /// its timestamps. Returns `false` if there is no such entry.
+    pub fn amend(&mut self, key: &str, value: &str) -> bool {
+        let Some(entry) = self.pairs.get_mut(key) else { return false };
+        entry.amend(value);
+        true
+    }
+
+    /// Make version `k` of an entry its current value.
+    /// Returns `false` if there is no such entry or version.
+    pub fn restore(&mut self, key: &str, k: usize) -> bool {
+        let Some(entry) = self.pairs.get_mut(key) else { return false };
+        match entry.version(k) {
+            Some(version) => {
+                entry.update(&version.value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move an entry (with its history) to a new name.
+    pub fn rename(&mut self, current: &str, next: &str) -> bool {
+        match self.pairs.remove(current) {
+            Some(entry) => {
+                self.pairs.insert(next.to_string(), entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Add a whole entry, with its history and attachments. An entry
+    /// already under that name is moved to the trash.
+    pub fn put(&mut self, key: &str, entry: Entry) {
+        self.remove(key);
+        self.pairs.insert(key.to_string(), entry);
+    }
+
+    /// Remove an entry, moving it to the trash.
+    pub fn remove(&mut self, key: &str) -> Option<Zeroizing<String>> {
+        let entry = self.pairs.remove(key)?;
+        let value = Zeroizing::new(entry.value().to_string());
+        self.trash.push(Trashed {
+            name: key.to_string(),
+            removed: timestamp::now(),
+            entry,
+        });
+        Some(value)
+    }
+
+    pub fn trash(&self) -> &[Trashed] {
+        &self.trash
+    }
+
+    /// Bring back the most recently trashed entry with this name.
+    /// Returns `false` if nothing was restored.
+    pub fn untrash(&mut self, key: &str) -> bool {
+        if self.has_name(key) {
+            return false;
+        }
+        let Some(i) = self.trash.iter().rposition(|v| v.name == key) else {
+            return false;
+        };
+        let trashed = self.trash.remove(i);
+        self.pairs.insert(trashed.name, trashed.entry);
+        true
+    }
+
+    /// Permanently delete trashed entries. With a name, only entries of
+    /// that name are purged. Returns the number of entries purged.
+    pub fn purge(&mut self, key: Option<&str>) -> usize {
+        let before = self.trash.len();
+        match key {
+            Some(key) => self.trash.retain(|v| v.name != key),
+            None => self.trash.clear(),
+        }
+        before - self.trash.len()
     }
 }
 
 /// Read/write operations
 impl Database {
     fn default_dir() -> PathBuf {
-        dirs::config_dir().unwrap().join("pass")
+        Config::default_dir()
     }
 
-    pub fn path() -> PathBuf {
-        const FILENAME: &str = "pass.store";
-        if let Ok(pass_dir) = env::var("PASSWORD_STORE_DIR") {
-            PathBuf::from(pass_dir).join(FILENAME)
-        } else {
-            Database::default_dir().join(FILENAME)
+    /// Path of the store file, in the directory set by `store.dir` (see
+    /// `Config`) or the default one. Fails if the config file is invalid.
+    pub fn path() -> Result<PathBuf> {
+        Ok(Self::path_in(&Config::load()?))
+    }
+
+    /// Like `path`, with the settings of `config`
+    pub fn path_in(config: &Config) -> PathBuf {
+        Self::locate(config).0
+    }
+
+    /// Path of the store file, along with the setting that chose it:
+    /// a flag, an environment variable, the config file, or the default
+    /// directory.
+    pub fn locate(config: &Config) -> (PathBuf, Source) {
+        match config.get("store.dir") {
+            Ok(Some((dir, source))) => (Self::path_at(Path::new(&dir)), source),
+            _ => (Self::path_at(&Self::default_dir()), Source::Default),
         }
     }
 
+    /// Path of the store file in `dir`
+    pub fn path_at(dir: &Path) -> PathBuf {
+        dir.join("pass.store")
+    }
+
     pub fn read() -> Result<Self> {
-        let db_path = Database::path();
-        eprintln!("--> {db_path:?}");
-        Self::read_from_file(&db_path)
+        Self::read_from_file(&Database::path()?)
+    }
+
+    /// Read the format marker and return the format version. Version 1
+    /// files have no marker, so the reader is rewound for them.
+    fn read_version<R: Read + Seek>(reader: &mut R) -> Result<u8> {
+        let mut magic = [0u8; 4];
+        let found = match reader.read_exact(&mut magic) {
+            Ok(()) => &magic == MAGIC,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => false,
+            Err(e) => return Err(e.into()),
+        };
+        if !found {
+            reader.rewind()?;
+            return Ok(1);
+        }
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        match version[0] {
+            FORMAT_VERSION => Ok(FORMAT_VERSION),
+            v => Err(Error::UnsupportedVersion(v)),
+        }
     }
 
-    fn read_gpg_id<R: Read>(reader: &mut R) -> Result<String> {
-        let bytes = reader.sized_read()?;
-        let gpg_id = String::from_utf8_lossy(&bytes);
-        Ok(gpg_id.to_string())
+    /// Read a length-prefixed section in the framing of `version`
+    fn read_frame<R: Read>(reader: &mut R, version: u8) -> Result<Vec<u8>> {
+        Ok(match version {
+            1 => reader.sized_read()?,
+            _ => reader.sized_read_u32(MAX_FRAME)?,
+        })
     }
 
-    fn read_header<R: Read>(reader: &mut R, gpg: &Gpg) -> Result<Header> {
-        eprintln!("Get encoded data...");
-        let enc_header_data = reader.sized_read()?;
-        eprintln!("Decode header data...");
-        let header_data = gpg.decrypt(&enc_header_data)?;
-        eprintln!("Build header...");
-        Header::try_from(&header_data)
+    /// Read the unencrypted start of a store, leaving `reader` at the
+    /// start of the body.
+    pub fn read_preamble<R: Read + Seek>(reader: &mut R) -> Result<Preamble> {
+        let version = Self::read_version(reader)?;
+        let gpg_id = Self::read_frame(reader, version)?;
+        let gpg_id = String::from_utf8_lossy(&gpg_id).to_string();
+        let header = Self::read_frame(reader, version)?;
+        Ok(Preamble { version, gpg_id, header })
     }
 
-    pub fn read_from_file(data_file: &PathBuf) -> Result<Self> {
+    fn decrypt_header(preamble: &Preamble) -> Result<Header> {
+        if let Some(header) = agent::get(&preamble.header) {
+            return Ok(header);
+        }
+        let header_data =
+            Gpg::new(&preamble.gpg_id).decrypt(&preamble.header)?;
+        let header = Header::try_from(&header_data[..])?;
+        agent::put(&preamble.header, &header);
+        Ok(header)
+    }
+
+    /// Parse a decrypted body
+    pub fn from_body(body: &[u8]) -> Result<Self> {
+        let limit = attachment::MAX_TOTAL_SIZE;
+        Ok(attachment::with_budget(limit, || serde_json::from_slice(body))?)
+    }
+
+    /// Total size of the attachments of every entry, trashed ones
+    /// included, before compression.
+    pub fn attachments_size(&self) -> u64 {
+        let entries =
+            self.pairs.values().chain(self.trash.iter().map(|v| &v.entry));
+        entries.flat_map(|v| v.attachments()).map(|v| v.size()).sum()
+    }
+
+    pub fn read_from_file(data_file: &Path) -> Result<Self> {
         let mut reader = File::open(data_file).map_err(|e| match e.kind() {
             io::ErrorKind::NotFound => Error::DataFileNotFound,
             _ => Error::IoError(e),
         })?;
 
-        eprintln!("Reading GPG ID...");
-        let gpg_id = Self::read_gpg_id(&mut reader)?;
-
-        eprintln!("Creating GPG...");
-        let gpg = Gpg::new(&gpg_id);
-
-        eprintln!("using GPG ID: [{gpg_id}]");
-
-        eprintln!("Reading header...");
-        let header = Self::read_header(&mut reader, &gpg)?;
+        let preamble = Self::read_preamble(&mut reader)?;
 
-        eprintln!("getting reader...");
-        // wrap the reader in the header's decryptor
-        let reader = ChaReader::new(reader, header.cipher());
+        let header = Self::decrypt_header(&preamble)?;
 
-        let mut db = serde_json::from_reader::<_, Database>(reader)?;
-        db.gpg_id = Some(gpg_id);
+        // decrypt the body into a wiped, unswappable buffer
+        let len = reader.metadata()?.len() - reader.stream_position()?;
+        let mut body = Locked::new(len as usize);
+        ChaReader::new(reader, header.cipher()).read_exact(&mut body)?;
+        let mut db = Self::from_body(&body)?;
+        db.hide_gpg_id = preamble.gpg_id.is_empty();
+        if !db.hide_gpg_id {
+            db.gpg_id = Some(preamble.gpg_id);
+        }
         Ok(db)
     }
 
     pub fn write(&self) -> Result<()> {
+        if self.gpg_id().is_none() {
+            return Err(Error::GpgIdNotFound);
+        }
+        self.write_to_file(&Database::path()?)
+    }
+
+    /// Encrypt and write the database to `path`, creating its directory
+    /// if needed.
+    pub fn write_to_file(&self, path: &Path) -> Result<()> {
         let gpg_id = match self.gpg_id() {
             None => return Err(Error::GpgIdNotFound),
             Some(v) => v,
         };
-        let dir = Database::default_dir();
-        if !dir.is_dir() {
-            fs::create_dir_all(dir)?;
+        if let Some(dir) = path.parent() {
+            create_private_dir(dir)?;
         }
-        let mut writer = File::create(Database::path())?;
+        let mut writer = File::create(path)?;
 
-        writer.sized_write(gpg_id.as_bytes())?;
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        match self.hide_gpg_id {
+            true => writer.sized_write_u32(&[])?,
+            false => writer.sized_write_u32(gpg_id.as_bytes())?,
+        }
 
-        let gpg = Gpg::new(gpg_id);
+        let gpg = Gpg::new(gpg_id).hide_recipient(self.hide_gpg_id);
 
         let header = Header::generate();
 
         let enc_header_data = gpg.encrypt(header.as_bytes())?;
 
-        writer.sized_write(&enc_header_data)?;
+        writer.sized_write_u32(&enc_header_data)?;
+        agent::put(&enc_header_data, &header);
 
         // final write
         let writer = ChaWriter::new(writer, header.cipher());
-        serde_json::to_writer::<_, Database>(writer, &self).unwrap();
+        serde_json::to_writer::<_, Database>(writer, self)?;
 
         Ok(())
     }
 }
+
+/// Create `dir` and its missing parents, readable only by this user.
+/// Existing directories are left as they are.
+pub(crate) fn create_private_dir(dir: &Path) -> io::Result<()> {
+    if dir.as_os_str().is_empty() || dir.is_dir() {
+        return Ok(());
+    }
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)
+}
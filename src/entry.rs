@@ -0,0 +1,309 @@
+use crate::attachment::Attachment;
+use crate::timestamp;
+
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Number of previous versions kept for each entry
+pub const HISTORY_LIMIT: usize = 10;
+
+/// A stored value along with the time it was set.
+#[derive(Serialize, Deserialize, Debug, Clone, Zeroize, ZeroizeOnDrop)]
+pub struct Version {
+    pub value: String,
+    pub modified: u64,
+}
+
+/// A single entry in the database. `history` holds previous versions,
+/// most recent first. Values are zeroed on drop.
+#[derive(Serialize, Deserialize, Debug, Clone, Zeroize, ZeroizeOnDrop)]
+#[serde(from = "EntryRepr")]
+pub struct Entry {
+    value: String,
+    modified: u64,
+    password_changed: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    history: Vec<Version>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<Attachment>,
+}
+
+impl Entry {
+    pub fn new(value: &str) -> Self {
+        let now = timestamp::now();
+        Self {
+            value: value.to_string(),
+            modified: now,
+            password_changed: now,
+            history: vec![],
+            attachments: vec![],
+        }
+    }
+
+    pub fn value(&self) -> &String {
+        &self.value
+    }
+
+    /// When the current value was set, in seconds since the epoch. 0 if
+    /// unknown.
+    pub fn modified(&self) -> u64 {
+        self.modified
+    }
+
+    /// When the password (the first line of the value) was last changed,
+    /// in seconds since the epoch. 0 if unknown.
+    pub fn password_changed(&self) -> u64 {
+        self.password_changed
+    }
+
+    /// Replace the current value, pushing the old one into history.
+    pub fn update(&mut self, value: &str) {
+        if self.value == value {
+            return;
+        }
+        let now = timestamp::now();
+        if password(&self.value) != password(value) {
+            self.password_changed = now;
+        }
+        let old = Version {
+            value: std::mem::replace(&mut self.value, value.to_string()),
+            modified: self.modified,
+        };
+        self.modified = now;
+        self.history.insert(0, old);
+        self.history.truncate(HISTORY_LIMIT);
+    }
+
+    /// Replace the current value without keeping a version or touching
+    /// its timestamps, for bookkeeping such as tags and OTP counters.
+    pub fn amend(&mut self, value: &str) {
+        let _old =
+            Zeroizing::new(std::mem::replace(&mut self.value, value.into()));
+    }
+
+    /// A copy of the current value for another store. Previous values
+    /// and attachments are only carried over when asked for.
+    pub fn copy(&self, history: bool, attachments: bool) -> Entry {
+        let mut copy = Entry::new(&self.value);
+        copy.modified = self.modified;
+        copy.password_changed = self.password_changed;
+        if history {
+            copy.history = self.history.clone();
+        }
+        if attachments {
+            copy.attachments = self.attachments.clone();
+        }
+        copy
+    }
+
+    pub fn attachments(&self) -> &[Attachment] {
+        &self.attachments
+    }
+
+    pub fn attachment(&self, name: &str) -> Option<&Attachment> {
+        self.attachments.iter().find(|v| v.name == name)
+    }
+
+    /// Add an attachment, replacing any with the same name.
+    pub fn attach(&mut self, attachment: Attachment) {
+        self.detach(&attachment.name);
+        self.attachments.push(attachment);
+    }
+
+    /// Remove an attachment. Returns `false` if there was none.
+    pub fn detach(&mut self, name: &str) -> bool {
+        let count = self.attachments.len();
+        self.attachments.retain(|v| v.name != name);
+        self.attachments.len() != count
+    }
+
+    /// Number of versions available, including the current one.
+    pub fn version_count(&self) -> usize {
+        self.history.len() + 1
+    }
+
+    /// Version 0 is the current value, 1 is the one before it, and so on.
+    pub fn version(&self, k: usize) -> Option<Version> {
+        match k {
+            0 => Some(Version {
+                value: self.value.clone(),
+                modified: self.modified,
+            }),
+            k => self.history.get(k - 1).cloned(),
+        }
+    }
+}
+
+/// Accepts both the current layout and the plain strings written by
+/// stores that predate entry history.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EntryRepr {
+    Legacy(String),
+    Current {
+        value: String,
+        modified: u64,
+        #[serde(default)]
+        password_changed: Option<u64>,
+        #[serde(default)]
+        history: Vec<Version>,
+        #[serde(default)]
+        attachments: Vec<Attachment>,
+    },
+}
+
+impl From<EntryRepr> for Entry {
+    fn from(repr: EntryRepr) -> Self {
+        match repr {
+            EntryRepr::Legacy(value) => Entry {
+                value,
+                modified: 0,
+                password_changed: 0,
+                history: vec![],
+                attachments: vec![],
+            },
+            EntryRepr::Current {
+                value,
+                modified,
+                password_changed,
+                history,
+                attachments,
+            } => Entry {
+                value,
+                modified,
+                password_changed: password_changed.unwrap_or(modified),
+                history,
+                attachments,
+            },
+        }
+    }
+}
+
+/// An entry that was removed from the database.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Trashed {
+    pub name: String,
+    pub removed: u64,
+    pub entry: Entry,
+}
+
+/// The password of a stored value: its first line.
+pub fn password(value: &str) -> &str {
+    value.split('\n').next().unwrap_or("")
+}
+
+/// The `<key>: <value>` metadata lines that follow the password. Lines
+/// that are not of that form continue the previous field's value.
+pub fn fields(value: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = vec![];
+    for line in value.split('\n').skip(1) {
+        match (line.split_once(": "), fields.last_mut()) {
+            (Some((k, v)), _) if !k.is_empty() && !k.contains(' ') => {
+                fields.push((k.to_string(), v.to_string()))
+            }
+            (_, Some((_, v))) => {
+                v.push('\n');
+                v.push_str(line);
+            }
+            (_, None) => fields.push(("notes".to_string(), line.to_string())),
+        }
+    }
+    fields
+}
+
+/// The value of a field. `password` is the first line of the value.
+pub fn field(value: &str, key: &str) -> Option<String> {
+    if key == "password" {
+        return Some(password(value).to_string());
+    }
+    fields(value).into_iter().find(|v| v.0 == key).map(|v| v.1)
+}
+
+/// Tags of a stored value, from its `tags: <tag>, <tag>` field.
+pub fn tags(value: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    let list = field(value, "tags").unwrap_or_default();
+    for tag in list.split([',', ' ', '\n']).filter(|v| !v.is_empty()) {
+        if !tags.iter().any(|v| v == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// `value` with its `tags` field replaced, or removed if `tags` is empty.
+pub fn with_tags(value: &str, tags: &[String]) -> String {
+    let mut lines = value.split('\n');
+    let mut value = lines.next().unwrap_or("").to_string();
+    for line in lines.filter(|v| !v.starts_with("tags: ")) {
+        value.push('\n');
+        value.push_str(line);
+    }
+    if !tags.is_empty() {
+        value.push_str(&format!("\ntags: {}", tags.join(", ")));
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fields() {
+        let value = "pw\nuser: bob\nnote line\nurl: https://x\nfree text";
+        assert_eq!(password(value), "pw");
+        assert_eq!(field(value, "user").unwrap(), "bob\nnote line");
+        assert_eq!(field(value, "url").unwrap(), "https://x\nfree text");
+        assert_eq!(field("pw\nloose", "notes").unwrap(), "loose");
+        assert_eq!(field(value, "missing"), None);
+    }
+
+    #[test]
+    fn tags_round_trip() {
+        let value = "pw\ntags: prod, ops prod\nuser: bob";
+        assert_eq!(tags(value), ["prod", "ops"]);
+
+        let tagged = with_tags(value, &["db".to_string()]);
+        assert_eq!(tagged, "pw\nuser: bob\ntags: db");
+        assert_eq!(with_tags(&tagged, &[]), "pw\nuser: bob");
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut entry = Entry::new("0");
+        for i in 1..=HISTORY_LIMIT + 5 {
+            entry.update(&i.to_string());
+        }
+        assert_eq!(entry.version_count(), HISTORY_LIMIT + 1);
+        assert_eq!(entry.version(1).unwrap().value, "14");
+        entry.update("15");
+        assert_eq!(entry.version_count(), HISTORY_LIMIT + 1);
+    }
+
+    #[test]
+    fn amend_keeps_history_and_timestamps() {
+        let mut entry = Entry::new("pw");
+        entry.modified = 1;
+        entry.password_changed = 1;
+        entry.amend("pw\ntags: db");
+        assert_eq!(entry.version_count(), 1);
+        assert_eq!((entry.modified(), entry.password_changed()), (1, 1));
+
+        entry.update("pw\ntags: db\nuser: bob");
+        assert_eq!(entry.password_changed(), 1);
+        assert!(entry.modified() > 1);
+        entry.update("new\ntags: db\nuser: bob");
+        assert_eq!(entry.password_changed(), entry.modified());
+    }
+
+    #[test]
+    fn reads_legacy_values() {
+        let entry: Entry = serde_json::from_str("\"pw\"").unwrap();
+        assert_eq!((entry.value().as_str(), entry.modified()), ("pw", 0));
+
+        let entry: Entry =
+            serde_json::from_str(r#"{"value":"pw","modified":5}"#).unwrap();
+        assert_eq!(entry.password_changed(), 5);
+    }
+}
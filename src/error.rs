@@ -1,10 +1,81 @@
+use crate::sized_io::FrameTooLarge;
+
+use std::fmt;
+
 #[derive(Debug)]
+#[non_exhaustive]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     IoError(std::io::Error),
     SerdeJsonError(serde_json::Error),
+    CsvError(csv::Error),
+    ZipError(zip::result::ZipError),
+    InvalidImport(String),
+    InvalidOtp(String),
+    SshKeyError(ssh_key::Error),
+    SshAgentError(String),
     RawBytesReadError,
     GpgIdNotFound,
     DataFileNotFound,
+    NameExists(String),
+    NameNotFound(String),
+    FieldNotFound(String, String),
+    InvalidReference(String),
+    InvalidTemplate(String),
+    InvalidConfig(String),
+    StoreNotFound(String),
+    InvalidExpiry(String),
+    FrameTooLarge { len: u64, max: u64 },
+    UnsupportedVersion(u8),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::IoError(e) => write!(f, "I/O error: {e}"),
+            Error::SerdeJsonError(e) => write!(f, "Malformed data: {e}"),
+            Error::CsvError(e) => write!(f, "Malformed CSV: {e}"),
+            Error::ZipError(e) => write!(f, "Malformed archive: {e}"),
+            Error::InvalidImport(e) => write!(f, "Cannot import: {e}"),
+            Error::InvalidOtp(e) => write!(f, "Invalid otp settings: {e}"),
+            Error::SshKeyError(e) => write!(f, "Invalid SSH key: {e}"),
+            Error::SshAgentError(e) => write!(f, "ssh-agent: {e}"),
+            Error::RawBytesReadError => write!(f, "Failed to read raw bytes"),
+            Error::GpgIdNotFound => write!(f, "No GPG id set for the store"),
+            Error::DataFileNotFound => write!(f, "Data file not found"),
+            Error::NameExists(v) => write!(f, "An entry named [{v}] exists"),
+            Error::NameNotFound(v) => write!(f, "No entry named [{v}]"),
+            Error::FieldNotFound(name, field) => {
+                write!(f, "No field `{field}` in [{name}]")
+            }
+            Error::InvalidReference(e) => write!(f, "Invalid reference: {e}"),
+            Error::InvalidTemplate(e) => write!(f, "Invalid template:\n{e}"),
+            Error::InvalidConfig(e) => write!(f, "Invalid config: {e}"),
+            Error::StoreNotFound(v) => write!(f, "No store named `{v}`"),
+            Error::InvalidExpiry(e) => write!(f, "Invalid schedule: {e}"),
+            Error::FrameTooLarge { len, max } => write!(
+                f,
+                "Corrupt data file: frame of {len} bytes exceeds the {max} \
+                 byte limit"
+            ),
+            Error::UnsupportedVersion(v) => {
+                write!(f, "Data file format version {v} is not supported")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError(e) => Some(e),
+            Error::SerdeJsonError(e) => Some(e),
+            Error::CsvError(e) => Some(e),
+            Error::ZipError(e) => Some(e),
+            Error::SshKeyError(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 macro_rules! error {
@@ -17,7 +88,40 @@ macro_rules! error {
     };
 }
 
-error!(std::io::Error, Error::IoError);
 error!(serde_json::Error, Error::SerdeJsonError);
+error!(csv::Error, Error::CsvError);
+error!(zip::result::ZipError, Error::ZipError);
+error!(ssh_key::Error, Error::SshKeyError);
+
+/// Oversized frames are reported as such instead of as plain I/O errors.
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        let oversized = e.get_ref().and_then(|v| v.downcast_ref());
+        match oversized {
+            Some(&FrameTooLarge { len, limit }) => {
+                Error::FrameTooLarge { len, max: limit }
+            }
+            None => Error::IoError(e),
+        }
+    }
+}
+
+impl From<ssh_encoding::Error> for Error {
+    fn from(e: ssh_encoding::Error) -> Self {
+        Error::SshKeyError(e.into())
+    }
+}
+
+impl From<rsa::Error> for Error {
+    fn from(e: rsa::Error) -> Self {
+        Error::SshAgentError(e.to_string())
+    }
+}
+
+impl From<rsa::signature::Error> for Error {
+    fn from(e: rsa::signature::Error) -> Self {
+        Error::SshAgentError(e.to_string())
+    }
+}
 
 pub type Result<T> = std::result::Result<T, Error>;
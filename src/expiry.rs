@@ -0,0 +1,140 @@
+//! Rotation schedules, set with metadata fields in an entry's value:
+//!
+//! ```text
+//! hunter2
+//! rotate: 90d
+//! expires: 2025-06-30
+//! ```
+//!
+//! `rotate` is a period after the password was last changed, in days
+//! (`d`), weeks (`w`), months (`m`, 30 days) or years (`y`). `expires` is
+//! a fixed date. An entry is due at whichever comes first.
+
+use crate::entry::{self, Entry};
+use crate::{timestamp, Database, Error, Result};
+
+pub const DAY: u64 = 24 * 60 * 60;
+
+/// An entry with a rotation date
+#[derive(Debug)]
+pub struct Due {
+    pub name: String,
+    /// When the password should be rotated, in seconds since the epoch
+    pub due: u64,
+}
+
+/// Parse a period like `90d`, `12w`, `6m` or `1y` into seconds. A bare
+/// number is a number of days.
+pub fn parse_period(period: &str) -> Option<u64> {
+    let period = period.trim();
+    let (count, unit) = match period.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => period.split_at(i),
+        None => (period, "d"),
+    };
+    let days = match unit {
+        "d" => 1,
+        "w" => 7,
+        "m" => 30,
+        "y" => 365,
+        _ => return None,
+    };
+    count.parse::<u64>().ok()?.checked_mul(days * DAY)
+}
+
+/// When the password of `entry` is due for rotation, if it has a
+/// schedule.
+pub fn due_date(entry: &Entry) -> Result<Option<u64>> {
+    let value = entry.value();
+    let invalid = |field: &str, v: &str| {
+        Error::InvalidExpiry(format!("`{field}: {v}` is not valid"))
+    };
+
+    let mut due = None;
+    if let Some(v) = entry::field(value, "rotate") {
+        let period = parse_period(&v).ok_or_else(|| invalid("rotate", &v))?;
+        due = Some(entry.password_changed() + period);
+    }
+    if let Some(v) = entry::field(value, "expires") {
+        let date =
+            timestamp::parse_date(&v).ok_or_else(|| invalid("expires", &v))?;
+        due = Some(due.map_or(date, |d: u64| d.min(date)));
+    }
+    Ok(due)
+}
+
+/// Entries due for rotation before `until`, soonest first. Entries with
+/// a malformed schedule are returned as errors along with their names.
+pub fn due(db: &Database, until: u64) -> (Vec<Due>, Vec<(String, Error)>) {
+    let (mut found, mut errors) = (vec![], vec![]);
+    for name in db.list_all() {
+        match due_date(db.entry(&name).unwrap()) {
+            Ok(Some(due)) if due <= until => found.push(Due { name, due }),
+            Ok(_) => {}
+            Err(e) => errors.push((name, e)),
+        }
+    }
+    found.sort_by(|a, b| a.due.cmp(&b.due).then_with(|| a.name.cmp(&b.name)));
+    errors.sort_by(|a, b| a.0.cmp(&b.0));
+    (found, errors)
+}
+
+/// `value` with its password replaced and its fields kept. A fixed
+/// `expires` date is moved forward by the `rotate` period if there is
+/// one.
+pub fn rotated(value: &str, password: &str, now: u64) -> String {
+    let mut lines: Vec<String> =
+        value.split('\n').map(str::to_string).collect();
+    lines[0] = password.to_string();
+    let period = entry::field(value, "rotate").and_then(|v| parse_period(&v));
+    if let Some(period) = period {
+        for line in lines.iter_mut().skip(1) {
+            if line.starts_with("expires: ") {
+                *line = format!(
+                    "expires: {}",
+                    timestamp::format_date(now + period)
+                );
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn periods() {
+        assert_eq!(parse_period("90d"), Some(90 * DAY));
+        assert_eq!(parse_period(" 2w "), Some(14 * DAY));
+        assert_eq!(parse_period("6m"), Some(180 * DAY));
+        assert_eq!(parse_period("1y"), Some(365 * DAY));
+        assert_eq!(parse_period("30"), Some(30 * DAY));
+        for bad in ["", "d", "3h", "-1d", "99999999999999999999y"] {
+            assert_eq!(parse_period(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn due_at_the_earlier_date() {
+        let mut entry = Entry::new("a\nrotate: 10d\nexpires: 2000-01-01");
+        assert_eq!(due_date(&entry).unwrap(), Some(946684800));
+        entry.update("a\nexpires: someday");
+        assert!(matches!(due_date(&entry), Err(Error::InvalidExpiry(_))));
+        entry.update("a\nuser: bob");
+        assert_eq!(due_date(&entry).unwrap(), None);
+    }
+
+    #[test]
+    fn rotation_moves_expiry() {
+        let value = "old\nrotate: 1d\nexpires: 2000-01-01\nuser: bob";
+        assert_eq!(
+            rotated(value, "new", 946684800),
+            "new\nrotate: 1d\nexpires: 2000-01-02\nuser: bob"
+        );
+        assert_eq!(
+            rotated("old\nexpires: 2000-01-01", "new", 0),
+            "new\nexpires: 2000-01-01"
+        );
+    }
+}
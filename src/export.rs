@@ -0,0 +1,198 @@
+use crate::entry;
+use crate::gpg::Gpg;
+use crate::Result;
+
+use clap::ValueEnum;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path};
+use zeroize::Zeroizing;
+
+/// Output formats for `pass export`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// A JSON array of entries
+    Json,
+    /// CSV with name, password, username, url, totp and notes columns
+    Csv,
+    /// A zx2c4 `pass` directory of .gpg files
+    PasswordStore,
+    /// Bitwarden's unencrypted JSON import format
+    Bitwarden,
+}
+
+impl Format {
+    /// Formats that produce a single file rather than a directory
+    pub fn is_file(&self) -> bool {
+        *self != Format::PasswordStore
+    }
+}
+
+/// An entry to export: its name and its stored value.
+pub struct Item<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+/// Columns with a dedicated place in CSV and Bitwarden exports. Other
+/// fields are folded into the notes.
+const KNOWN: [&str; 5] = ["username", "url", "totp", "notes", "tags"];
+
+fn known<'a>(fields: &'a [(String, String)], key: &str) -> Vec<&'a str> {
+    fields.iter().filter(|v| v.0 == key).map(|v| v.1.as_str()).collect()
+}
+
+/// Notes along with every field that has no column of its own
+fn notes(fields: &[(String, String)]) -> String {
+    let mut notes = known(fields, "notes").join("\n");
+    for (k, v) in fields.iter().filter(|v| !KNOWN.contains(&v.0.as_str())) {
+        if !notes.is_empty() {
+            notes.push('\n');
+        }
+        notes.push_str(&format!("{k}: {v}"));
+    }
+    notes
+}
+
+pub fn json(items: &[Item]) -> Result<Zeroizing<Vec<u8>>> {
+    #[derive(Serialize)]
+    struct Json<'a> {
+        name: &'a str,
+        password: &'a str,
+        tags: Vec<String>,
+        fields: Vec<(String, String)>,
+    }
+    let items: Vec<_> = items
+        .iter()
+        .map(|v| Json {
+            name: v.name,
+            password: entry::password(v.value),
+            tags: entry::tags(v.value),
+            fields: entry::fields(v.value),
+        })
+        .collect();
+    Ok(Zeroizing::new(serde_json::to_vec_pretty(&items)?))
+}
+
+pub fn csv(items: &[Item]) -> Result<Zeroizing<Vec<u8>>> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record([
+        "name", "password", "username", "url", "totp", "notes", "tags",
+    ])?;
+    for item in items {
+        let fields = entry::fields(item.value);
+        writer.write_record([
+            item.name,
+            entry::password(item.value),
+            &known(&fields, "username").join(" "),
+            &known(&fields, "url").join(" "),
+            &known(&fields, "totp").join(" "),
+            &notes(&fields),
+            &entry::tags(item.value).join(","),
+        ])?;
+    }
+    writer.into_inner().map(Zeroizing::new).map_err(|e| e.into_error().into())
+}
+
+pub fn bitwarden(items: &[Item]) -> Result<Zeroizing<Vec<u8>>> {
+    let mut folders = BTreeMap::new();
+    let mut entries = vec![];
+    for item in items {
+        let (folder, title) = match item.name.rsplit_once('/') {
+            Some((folder, title)) => (Some(folder), title),
+            None => (None, item.name),
+        };
+        let folder_id =
+            folder.map(|v| folders.entry(v).or_insert_with(uuid).to_string());
+        let fields = entry::fields(item.value);
+        let first = |key| known(&fields, key).first().map(|v| v.to_string());
+        let notes = notes(&fields);
+        let tags = entry::tags(item.value);
+        let custom: Vec<_> = (!tags.is_empty())
+            .then(|| {
+                serde_json::json!({
+                    "name": "tags",
+                    "value": tags.join(", "),
+                    "type": 0,
+                })
+            })
+            .into_iter()
+            .collect();
+        entries.push(serde_json::json!({
+            "id": uuid(),
+            "folderId": folder_id,
+            "type": 1,
+            "name": title,
+            "notes": (!notes.is_empty()).then_some(notes),
+            "favorite": false,
+            "fields": custom,
+            "login": {
+                "username": first("username"),
+                "password": entry::password(item.value),
+                "totp": first("totp"),
+                "uris": known(&fields, "url")
+                    .iter()
+                    .map(|v| serde_json::json!({ "match": null, "uri": v }))
+                    .collect::<Vec<_>>(),
+            },
+        }));
+    }
+    let folders: Vec<_> = folders
+        .iter()
+        .map(|(name, id)| serde_json::json!({ "id": id, "name": name }))
+        .collect();
+    let export = serde_json::json!({
+        "encrypted": false,
+        "folders": folders,
+        "items": entries,
+    });
+    Ok(Zeroizing::new(serde_json::to_vec_pretty(&export)?))
+}
+
+/// Write each entry to `<dir>/<name>.gpg`, encrypted to `gpg_id`, along
+/// with the `.gpg-id` file that `pass` expects. Returns the names that
+/// were skipped because they would escape `dir`.
+pub fn password_store(
+    items: &[Item],
+    dir: &Path,
+    gpg_id: &str,
+) -> Result<Vec<String>> {
+    let gpg = Gpg::new(gpg_id);
+    let mut skipped = vec![];
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(".gpg-id"), format!("{gpg_id}\n"))?;
+    for item in items {
+        let relative = Path::new(item.name);
+        let safe =
+            relative.components().all(|v| matches!(v, Component::Normal(_)));
+        if !safe {
+            skipped.push(item.name.to_string());
+            continue;
+        }
+        let path = dir.join(format!("{}.gpg", item.name));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let ciphertext = gpg.encrypt(format!("{}\n", item.value))?;
+        fs::write(path, ciphertext)?;
+    }
+    Ok(skipped)
+}
+
+/// A random version 4 UUID
+fn uuid() -> String {
+    let mut bytes = rand::thread_rng().gen::<[u8; 16]>();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|v| format!("{v:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
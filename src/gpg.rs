@@ -1,31 +1,58 @@
 use std::io::Write;
 use std::process::{Command, Stdio};
 use std::{env, fs, io};
+use zeroize::Zeroizing;
+
+/// Environment variable naming the gpg program to run instead of `gpg`,
+/// e.g. `gpg2` or a stand-in for tests.
+pub const PROGRAM_ENV: &str = "PASS_GPG";
 
 pub struct Gpg {
     id: String,
+    hidden: bool,
 }
 
 impl Gpg {
     pub fn new(id: &str) -> Self {
-        Self { id: id.to_string() }
+        Self { id: id.to_string(), hidden: false }
+    }
+
+    /// Leave the recipient's key id out of encrypted data, like gpg's
+    /// `--hidden-recipient`. Decrypting then tries every secret key.
+    pub fn hide_recipient(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
     }
 
     fn cmd(&self) -> Command {
-        let mut cmd = Command::new("gpg");
+        let program = env::var_os(PROGRAM_ENV).unwrap_or("gpg".into());
+        let mut cmd = Command::new(program);
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
         cmd.args(["--compress-algo=none"]);
         cmd
     }
 
+    /// Whether the secret key for this id is in the keyring
+    pub fn has_secret_key(&self) -> bool {
+        let mut cmd = self.cmd();
+        cmd.args(["--list-secret-keys", &self.id]);
+        cmd.output().is_ok_and(|v| {
+            String::from_utf8_lossy(&v.stdout).contains(&self.id)
+        })
+    }
+
     pub fn encrypt<B: AsRef<[u8]>>(
         &self,
         plaintext: B,
     ) -> Result<Vec<u8>, io::Error> {
         let mut cmd = self.cmd();
         cmd.stdin(Stdio::piped());
-        cmd.args(["--recipient", &self.id, "--encrypt"]);
+        let recipient = match self.hidden {
+            true => "--hidden-recipient",
+            false => "--recipient",
+        };
+        cmd.args([recipient, &self.id, "--encrypt"]);
 
         let mut child = cmd.spawn()?;
         let mut stdin = child.stdin.take().unwrap();
@@ -33,13 +60,18 @@ impl Gpg {
         drop(stdin);
 
         let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(format!("gpg: {}", stderr.trim())));
+        }
         Ok(output.stdout)
     }
 
+    /// Decrypt `bytes`. The plaintext is wiped when dropped.
     pub fn decrypt<B: AsRef<[u8]>>(
         &self,
         bytes: B,
-    ) -> Result<Vec<u8>, io::Error> {
+    ) -> Result<Zeroizing<Vec<u8>>, io::Error> {
         let tmp_path = env::temp_dir().join("pass.tmp");
         fs::write(&tmp_path, bytes)?;
 
@@ -58,6 +90,10 @@ impl Gpg {
         };
         let output = child.wait_with_output()?;
         let _ = fs::remove_file(&tmp_path);
-        Ok(output.stdout)
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(format!("gpg: {}", stderr.trim())));
+        }
+        Ok(Zeroizing::new(output.stdout))
     }
 }
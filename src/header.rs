@@ -1,8 +1,10 @@
+use crate::memory::Locked;
 use crate::{Error, Result};
 
 use chacha20::cipher::KeyIvInit;
 use chacha20::ChaCha20;
 use rand::Rng;
+use zeroize::{Zeroize, Zeroizing};
 
 use std::ops::Range;
 
@@ -12,9 +14,11 @@ const CHACHA_NONCE_LEN: usize = 12;
 const CHACHA_KEY_RANGE: Range<usize> = 0..32;
 const CHACHA_NONCE_RANGE: Range<usize> = 32..44;
 
+/// The ChaCha20 key and nonce of a store. Kept in locked memory and
+/// zeroed on drop.
 #[derive(Debug)]
 pub struct Header {
-    data: [u8; HEADER_BYTE_LEN],
+    data: Locked,
 }
 
 impl Header {
@@ -22,26 +26,29 @@ impl Header {
         chacha_key: &[u8; CHACHA_KEY_LEN],
         chacha_nonce: &[u8; CHACHA_NONCE_LEN],
     ) -> Self {
-        let mut data = [0u8; HEADER_BYTE_LEN];
-        data[CHACHA_KEY_RANGE].copy_from_slice(chacha_key);
-        data[CHACHA_NONCE_RANGE].copy_from_slice(chacha_nonce);
-        Self { data }
+        let mut header = Self::zeroed();
+        header.data[CHACHA_KEY_RANGE].copy_from_slice(chacha_key);
+        header.data[CHACHA_NONCE_RANGE].copy_from_slice(chacha_nonce);
+        header
+    }
+
+    fn zeroed() -> Self {
+        Self { data: Locked::new(HEADER_BYTE_LEN) }
     }
 
     /// Generate a brand new Header
     pub fn generate() -> Self {
-        let mut rng = rand::thread_rng();
-        let chacha_key = rng.gen::<[u8; CHACHA_KEY_LEN]>();
-        let chacha_nonce = rng.gen::<[u8; CHACHA_NONCE_LEN]>();
-        Self::new(&chacha_key, &chacha_nonce)
+        let mut header = Self::zeroed();
+        rand::thread_rng().fill(&mut header.data[..]);
+        header
     }
 
     pub fn as_bytes(&self) -> &[u8] {
-        &self.data
+        &self.data[..]
     }
 
-    pub fn chacha_key(&self) -> [u8; CHACHA_KEY_LEN] {
-        self.data[CHACHA_KEY_RANGE].try_into().unwrap()
+    pub fn chacha_key(&self) -> Zeroizing<[u8; CHACHA_KEY_LEN]> {
+        Zeroizing::new(self.data[CHACHA_KEY_RANGE].try_into().unwrap())
     }
 
     pub fn chacha_nonce(&self) -> [u8; CHACHA_NONCE_LEN] {
@@ -49,25 +56,36 @@ impl Header {
     }
 
     pub fn cipher(&self) -> ChaCha20 {
-        ChaCha20::new(&self.chacha_key().into(), &self.chacha_nonce().into())
+        let key = self.chacha_key();
+        ChaCha20::new(key.as_ref().into(), &self.chacha_nonce().into())
     }
 }
 
 impl From<[u8; HEADER_BYTE_LEN]> for Header {
-    fn from(data: [u8; HEADER_BYTE_LEN]) -> Self {
-        Header { data }
+    fn from(mut data: [u8; HEADER_BYTE_LEN]) -> Self {
+        let mut header = Self::zeroed();
+        header.data.copy_from_slice(&data);
+        data.zeroize();
+        header
     }
 }
 
 impl TryFrom<&Vec<u8>> for Header {
     type Error = Error;
     fn try_from(data: &Vec<u8>) -> Result<Self> {
-        let mut fixed = [0u8; HEADER_BYTE_LEN];
+        Self::try_from(&data[..])
+    }
+}
+
+impl TryFrom<&[u8]> for Header {
+    type Error = Error;
+    fn try_from(data: &[u8]) -> Result<Self> {
         if data.len() != HEADER_BYTE_LEN {
             // TODO: fix. This curernt shows when the gpg key is not set yet
             return Err(Error::RawBytesReadError);
         }
-        fixed.copy_from_slice(&data);
-        Ok(Header { data: fixed })
+        let mut header = Self::zeroed();
+        header.data.copy_from_slice(data);
+        Ok(header)
     }
 }
@@ -0,0 +1,174 @@
+//! Lookups in a downloaded Have I Been Pwned password file: one
+//! `<SHA-1 in hex>:<count>` line per breached password, sorted by hash.
+//! The file is binary searched, so nothing is loaded into memory.
+
+use sha1::{Digest, Sha1};
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::Path;
+
+/// Below this many bytes, scan lines instead of bisecting
+const BLOCK: u64 = 4096;
+
+pub struct HashFile {
+    reader: BufReader<File>,
+    len: u64,
+}
+
+impl HashFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self { reader: BufReader::new(file), len })
+    }
+
+    /// Number of times `password` was seen in breaches, if at all
+    pub fn count(&mut self, password: &str) -> io::Result<Option<u64>> {
+        let hash = Sha1::digest(password.as_bytes());
+        let hex: String = hash.iter().map(|v| format!("{v:02X}")).collect();
+        self.lookup(&hex)
+    }
+
+    /// Find the line for an upper case hex hash.
+    fn lookup(&mut self, hash: &str) -> io::Result<Option<u64>> {
+        // lines starting at `lo` sort before `hash`, unless `lo` is 0, and
+        // lines starting at or after `hi` sort at or after it
+        let (mut lo, mut hi) = (0, self.len);
+        while hi - lo > BLOCK {
+            let mid = lo + (hi - lo) / 2;
+            let start = self.next_line(mid)?;
+            if start >= hi {
+                hi = mid;
+                continue;
+            }
+            match self.read_line()?.0.as_str() < hash {
+                true => lo = start,
+                false => hi = start,
+            }
+        }
+
+        self.reader.seek(SeekFrom::Start(lo))?;
+        loop {
+            let (key, count) = self.read_line()?;
+            if key.is_empty() || key.as_str() > hash {
+                return Ok(None);
+            }
+            if key == hash {
+                return Ok(Some(count));
+            }
+        }
+    }
+
+    /// Seek to the first line starting at or after `pos`, returning its
+    /// offset.
+    fn next_line(&mut self, pos: u64) -> io::Result<u64> {
+        if pos == 0 {
+            self.reader.seek(SeekFrom::Start(0))?;
+            return Ok(0);
+        }
+        self.reader.seek(SeekFrom::Start(pos - 1))?;
+        let skipped = self.reader.skip_until(b'\n')?;
+        Ok(pos - 1 + skipped as u64)
+    }
+
+    /// The hash and count of the next line. The hash is empty at the end
+    /// of the file.
+    fn read_line(&mut self) -> io::Result<(String, u64)> {
+        let mut line = String::new();
+        self.reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        let (key, count) = line.split_once(':').unwrap_or((line, ""));
+        Ok((key.to_ascii_uppercase(), count.parse().unwrap_or(0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A sorted hash file of `n` passwords, `password<i>` seen `i + 1`
+    /// times, along with its lines.
+    fn hash_file(
+        n: u64,
+        end: &str,
+    ) -> (tempfile::TempDir, HashFile, Vec<String>) {
+        let mut lines: Vec<String> = (0..n)
+            .map(|i| {
+                let hash = Sha1::digest(format!("password{i}").as_bytes());
+                let hex: String =
+                    hash.iter().map(|v| format!("{v:02X}")).collect();
+                format!("{hex}:{}", i + 1)
+            })
+            .collect();
+        lines.sort();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hashes.txt");
+        fs::write(&path, lines.join("\n") + end).unwrap();
+        (dir, HashFile::open(&path).unwrap(), lines)
+    }
+
+    fn split(line: &str) -> (&str, u64) {
+        let (hash, count) = line.split_once(':').unwrap();
+        (hash, count.parse().unwrap())
+    }
+
+    #[test]
+    fn finds_every_line() {
+        for end in ["\n", ""] {
+            let (_dir, mut file, lines) = hash_file(3000, end);
+            assert!(file.len > 20 * BLOCK);
+            for line in &lines {
+                let (hash, count) = split(line);
+                assert_eq!(file.lookup(hash).unwrap(), Some(count), "{line}");
+            }
+            assert_eq!(file.count("password0").unwrap(), Some(1));
+            assert_eq!(file.count("password2999").unwrap(), Some(3000));
+        }
+    }
+
+    #[test]
+    fn misses_between_neighbours() {
+        let (_dir, mut file, lines) = hash_file(3000, "");
+        assert_eq!(file.lookup(&"0".repeat(40)).unwrap(), None);
+        assert_eq!(file.lookup(&"F".repeat(40)).unwrap(), None);
+        assert_eq!(file.count("not in the file").unwrap(), None);
+
+        for pair in lines.windows(2) {
+            let (a, b) = (split(&pair[0]).0, split(&pair[1]).0);
+            // `a` with its last digit bumped sorts between the two
+            let next = match a.as_bytes()[39] {
+                b'9' => 'A',
+                b'F' => continue,
+                v => (v + 1) as char,
+            };
+            let between = format!("{}{next}", &a[..39]);
+            assert!(a < between.as_str() && between.as_str() < b);
+            assert_eq!(file.lookup(&between).unwrap(), None, "{between}");
+        }
+    }
+
+    #[test]
+    fn parses_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hashes.txt");
+        let hash = |c: char| c.to_string().repeat(40);
+        let text = format!(
+            "{}:7\r\n{}:0012\n{}\n{}:many\n{}:18446744073709551615",
+            hash('1'),
+            hash('2'),
+            hash('3'),
+            hash('4'),
+            hash('5'),
+        );
+        fs::write(&path, text.to_lowercase()).unwrap();
+        let mut file = HashFile::open(&path).unwrap();
+        assert_eq!(file.lookup(&hash('1')).unwrap(), Some(7));
+        assert_eq!(file.lookup(&hash('2')).unwrap(), Some(12));
+        assert_eq!(file.lookup(&hash('3')).unwrap(), Some(0));
+        assert_eq!(file.lookup(&hash('4')).unwrap(), Some(0));
+        assert_eq!(file.lookup(&hash('5')).unwrap(), Some(u64::MAX));
+        assert_eq!(file.lookup(&hash('6')).unwrap(), None);
+    }
+}
@@ -0,0 +1,113 @@
+use super::{Parsed, Record};
+use crate::Result;
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+const LOGIN: u8 = 1;
+const SECURE_NOTE: u8 = 2;
+
+#[derive(Deserialize)]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    folders: Vec<Folder>,
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Folder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    #[serde(rename = "type")]
+    kind: u8,
+    name: String,
+    folder_id: Option<String>,
+    notes: Option<String>,
+    login: Option<Login>,
+    #[serde(default)]
+    fields: Vec<Field>,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    username: Option<String>,
+    password: Option<String>,
+    totp: Option<String>,
+    #[serde(default)]
+    uris: Vec<Uri>,
+}
+
+#[derive(Deserialize)]
+struct Uri {
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Field {
+    name: Option<String>,
+    value: Option<String>,
+}
+
+/// Read an unencrypted Bitwarden JSON export.
+pub fn read(path: &Path) -> Result<Parsed> {
+    let reader = BufReader::new(File::open(path)?);
+    let export: Export = serde_json::from_reader(reader)?;
+    let mut parsed = Parsed::default();
+    if export.encrypted {
+        parsed.skip("*", "encrypted exports are not supported");
+        return Ok(parsed);
+    }
+
+    let folders: HashMap<_, _> = export
+        .folders
+        .iter()
+        .map(|v| (v.id.as_str(), v.name.as_str()))
+        .collect();
+
+    for item in export.items {
+        if item.kind != LOGIN && item.kind != SECURE_NOTE {
+            parsed.skip(&item.name, "unsupported item type");
+            continue;
+        }
+        let folder = item
+            .folder_id
+            .as_deref()
+            .and_then(|v| folders.get(v))
+            .unwrap_or(&"");
+        let login = item.login.as_ref();
+        let password = login.and_then(|v| v.password.as_deref());
+        let mut record =
+            Record::new(folder, &item.name, password.unwrap_or(""))
+                .field(
+                    "username",
+                    login.and_then(|v| v.username.as_deref()).unwrap_or(""),
+                )
+                .field(
+                    "totp",
+                    login.and_then(|v| v.totp.as_deref()).unwrap_or(""),
+                );
+        for uri in login.map_or(&[][..], |v| &v.uris) {
+            record = record.field("url", uri.uri.as_deref().unwrap_or(""));
+        }
+        for field in &item.fields {
+            let name = field.name.as_deref().unwrap_or("");
+            if !name.is_empty() {
+                record =
+                    record.field(name, field.value.as_deref().unwrap_or(""));
+            }
+        }
+        record = record.field("notes", item.notes.as_deref().unwrap_or(""));
+        parsed.push(record);
+    }
+    Ok(parsed)
+}
@@ -0,0 +1,103 @@
+use super::{Parsed, Record};
+use crate::error::Error;
+use crate::Result;
+
+use std::path::Path;
+
+/// Column layouts of known CSV exports
+#[derive(Clone, Copy, Debug)]
+pub enum Preset {
+    /// Detect columns from the header row
+    Generic,
+    /// KeePassXC's CSV export
+    Keepassxc,
+    /// 1Password's CSV export
+    OnePassword,
+}
+
+/// Header names recognized for each column, compared case-insensitively.
+const COLUMNS: [(&str, &[&str]); 8] = [
+    ("name", &["name", "title"]),
+    ("password", &["password", "pass", "login_password"]),
+    ("username", &["username", "user", "login", "login_username"]),
+    ("url", &["url", "website", "uri", "login_uri"]),
+    ("notes", &["notes", "note", "extra"]),
+    ("folder", &["folder", "group", "grouping", "vault"]),
+    ("totp", &["totp", "otpauth", "otp", "login_totp"]),
+    ("tags", &["tags", "tag", "labels"]),
+];
+
+/// Find the index of each known column. `mapping` pairs a column
+/// (`name`, `password`, ...) with the header used for it in this file and
+/// takes priority over the built-in header names.
+fn locate(
+    headers: &csv::StringRecord,
+    mapping: &[(String, String)],
+) -> Result<[Option<usize>; 8]> {
+    let find = |header: &str| {
+        headers.iter().position(|v| v.trim().eq_ignore_ascii_case(header))
+    };
+    let mut found = [None; 8];
+    for (i, (_, aliases)) in COLUMNS.iter().enumerate() {
+        found[i] = aliases.iter().find_map(|v| find(v));
+    }
+    for (column, header) in mapping {
+        let Some(i) = COLUMNS.iter().position(|v| v.0 == column) else {
+            return Err(Error::InvalidImport(format!(
+                "unknown column {column}"
+            )));
+        };
+        found[i] = match find(header) {
+            Some(v) => Some(v),
+            None => {
+                let msg = format!("no header named {header}");
+                return Err(Error::InvalidImport(msg));
+            }
+        };
+    }
+    if found[0].is_none() || found[1].is_none() {
+        let msg = "could not find name and password columns";
+        return Err(Error::InvalidImport(msg.into()));
+    }
+    Ok(found)
+}
+
+/// Read a CSV export with a header row.
+pub fn read(
+    path: &Path,
+    preset: Preset,
+    mapping: &[(String, String)],
+) -> Result<Parsed> {
+    let mut reader =
+        csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let columns = locate(&headers, mapping)?;
+    let archived =
+        headers.iter().position(|v| v.eq_ignore_ascii_case("archived"));
+    let mut parsed = Parsed::default();
+
+    for row in reader.records() {
+        let row = row?;
+        let get = |i: usize| columns[i].and_then(|v| row.get(v)).unwrap_or("");
+        let mut folder = get(5);
+        if let Preset::Keepassxc = preset {
+            // KeePassXC puts every group under the database's root group
+            folder = match folder {
+                "Root" => "",
+                v => v.strip_prefix("Root/").unwrap_or(v),
+            };
+        }
+        if archived.and_then(|v| row.get(v)) == Some("true") {
+            parsed.skip(get(0), "archived item");
+            continue;
+        }
+        let record = Record::new(folder, get(0), get(1))
+            .field("username", get(2))
+            .field("url", get(3))
+            .field("totp", get(6))
+            .field("notes", get(4))
+            .tags(&get(7).split([',', ';']).collect::<Vec<_>>());
+        parsed.push(record);
+    }
+    Ok(parsed)
+}
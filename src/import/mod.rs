@@ -0,0 +1,299 @@
+mod bitwarden;
+mod csv_file;
+mod onepassword;
+mod password_store;
+
+pub use csv_file::Preset;
+pub use password_store::scan as scan_password_store;
+
+use crate::database::Database;
+use crate::Result;
+
+use clap::ValueEnum;
+use std::path::Path;
+
+/// What to do when an imported name already exists in the database
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Conflict {
+    /// Keep the existing entry
+    Skip,
+    /// Replace the existing entry, keeping the old value in its history
+    Overwrite,
+    /// Import under a new name
+    Rename,
+}
+
+/// The outcome of resolving an imported name against the database
+pub enum Action {
+    Insert(String),
+    Overwrite(String),
+    Skip,
+}
+
+/// Decide what to do with an incoming entry called `name`.
+pub fn resolve(db: &Database, name: &str, conflict: Conflict) -> Action {
+    if !db.has_name(name) {
+        return Action::Insert(name.to_string());
+    }
+    match conflict {
+        Conflict::Skip => Action::Skip,
+        Conflict::Overwrite => Action::Overwrite(name.to_string()),
+        Conflict::Rename => (1..)
+            .map(|n| format!("{name}-{n}"))
+            .find(|v| !db.has_name(v))
+            .map_or(Action::Skip, Action::Insert),
+    }
+}
+
+/// A single entry read from an export, ready to be inserted.
+#[derive(Debug)]
+pub struct Record {
+    pub name: String,
+    pub value: String,
+}
+
+impl Record {
+    /// Build a record named `<folder>/<title>`. The password goes on the
+    /// first line, followed by metadata lines added with `field`.
+    pub fn new(folder: &str, title: &str, password: &str) -> Self {
+        let name = [folder, title]
+            .iter()
+            .flat_map(|v| v.split('/'))
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<_>>()
+            .join("/");
+        Self { name, value: password.to_string() }
+    }
+
+    /// Add a `tags` line, if there are any.
+    pub fn tags<S: AsRef<str>>(self, tags: &[S]) -> Self {
+        let tags: Vec<_> = tags.iter().map(|v| v.as_ref().trim()).collect();
+        self.field("tags", &tags.join(", "))
+    }
+
+    /// Append a `<key>: <value>` metadata line. Empty values are ignored.
+    pub fn field(mut self, key: &str, value: &str) -> Self {
+        let value = value.trim();
+        if !value.is_empty() {
+            self.value.push_str(&format!("\n{key}: {value}"));
+        }
+        self
+    }
+}
+
+/// Everything read from an export: the records to insert, and the
+/// entries that could not be converted along with the reason why.
+#[derive(Default, Debug)]
+pub struct Parsed {
+    pub records: Vec<Record>,
+    pub skipped: Vec<(String, String)>,
+}
+
+impl Parsed {
+    pub fn push(&mut self, record: Record) {
+        if record.name.is_empty() {
+            self.skip("<untitled>", "missing name");
+        } else {
+            self.records.push(record);
+        }
+    }
+
+    pub fn skip(&mut self, name: &str, reason: &str) {
+        self.skipped.push((name.to_string(), reason.to_string()));
+    }
+}
+
+pub fn bitwarden(path: &Path) -> Result<Parsed> {
+    bitwarden::read(path)
+}
+
+/// 1Password exports: `.1pux` archives or CSV files.
+pub fn onepassword(path: &Path) -> Result<Parsed> {
+    match path.extension().is_some_and(|v| v == "1pux") {
+        true => onepassword::read_1pux(path),
+        false => csv_file::read(path, Preset::OnePassword, &[]),
+    }
+}
+
+pub fn csv(
+    path: &Path,
+    preset: Preset,
+    mapping: &[(String, String)],
+) -> Result<Parsed> {
+    csv_file::read(path, preset, mapping)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/import")
+            .join(name)
+    }
+
+    fn records(parsed: &Parsed) -> Vec<(&str, &str)> {
+        let records = parsed.records.iter();
+        records.map(|v| (v.name.as_str(), v.value.as_str())).collect()
+    }
+
+    fn skipped(parsed: &Parsed) -> Vec<(&str, &str)> {
+        let skipped = parsed.skipped.iter();
+        skipped.map(|v| (v.0.as_str(), v.1.as_str())).collect()
+    }
+
+    #[test]
+    fn reads_keepassxc() {
+        let parsed =
+            csv(&fixture("keepassxc.csv"), Preset::Keepassxc, &[]).unwrap();
+        assert_eq!(
+            records(&parsed),
+            [
+                (
+                    "mail",
+                    "s3cret\nusername: alice\nurl: https://mail.example.com"
+                ),
+                (
+                    "Web/github",
+                    "hunter2\nusername: alice\nurl: https://github.com\n\
+                     totp: otpauth://totp/github?secret=JBSWY3DPEHPK3PXP\n\
+                     notes: line one\nline two"
+                ),
+                ("Rooted/bank", "pin"),
+            ]
+        );
+        assert_eq!(skipped(&parsed), [("<untitled>", "missing name")]);
+    }
+
+    #[test]
+    fn reads_generic_csv() {
+        let mapping = [("notes".to_string(), "Note".to_string())];
+        let path = fixture("generic.csv");
+        let parsed = csv(&path, Preset::Generic, &mapping).unwrap();
+        assert_eq!(
+            records(&parsed),
+            [
+                (
+                    "Work/vpn",
+                    "tunnel\nusername: bob\nurl: https://vpn.example.com\n\
+                     tags: ops, prod"
+                ),
+                ("wifi", "guest\nnotes: ask at the desk"),
+            ]
+        );
+
+        let mapping = [("notes".to_string(), "Remarks".to_string())];
+        assert!(csv(&path, Preset::Generic, &mapping).is_err());
+    }
+
+    #[test]
+    fn reads_onepassword_csv() {
+        let parsed = onepassword(&fixture("1password.csv")).unwrap();
+        assert_eq!(
+            records(&parsed),
+            [(
+                "Example",
+                "pa55\nusername: carol\nurl: https://example.com\n\
+                 notes: a note\ntags: work, shared"
+            )]
+        );
+        assert_eq!(skipped(&parsed), [("Old account", "archived item")]);
+    }
+
+    #[test]
+    fn reads_onepassword_1pux() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.1pux");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("export.data", options).unwrap();
+        zip.write_all(&fs::read(fixture("1pux-export.data")).unwrap()).unwrap();
+        zip.finish().unwrap();
+
+        let parsed = onepassword(&path).unwrap();
+        assert_eq!(
+            records(&parsed),
+            [
+                (
+                    "Personal/forum",
+                    "chat\nusername: erin\nurl: https://forum.example.com\n\
+                     tags: hobby"
+                ),
+                ("Personal/router", "admin"),
+            ]
+        );
+        assert_eq!(
+            skipped(&parsed),
+            [("old forum", "archived item"), ("spam", "deleted item")]
+        );
+    }
+
+    #[test]
+    fn reads_bitwarden() {
+        let parsed = bitwarden(&fixture("bitwarden.json")).unwrap();
+        assert_eq!(
+            records(&parsed),
+            [
+                (
+                    "Social/mastodon",
+                    "toot\nusername: dave\ntotp: JBSWY3DPEHPK3PXP\n\
+                     url: https://mastodon.social\npin: 1234\n\
+                     notes: recovery codes in the safe"
+                ),
+                ("door code", "\nnotes: 4321"),
+            ]
+        );
+        assert_eq!(
+            skipped(&parsed),
+            [("visa", "unsupported item type"), ("<untitled>", "missing name"),]
+        );
+
+        let parsed = bitwarden(&fixture("bitwarden-encrypted.json")).unwrap();
+        assert!(parsed.records.is_empty());
+        assert_eq!(
+            skipped(&parsed),
+            [("*", "encrypted exports are not supported")]
+        );
+    }
+
+    #[test]
+    fn scans_password_store() {
+        let dir = fixture("password-store");
+        let found = scan_password_store(&dir).unwrap();
+        assert_eq!(
+            found,
+            [
+                ("mail".to_string(), dir.join("mail.gpg")),
+                ("web/github".to_string(), dir.join("web/github.gpg")),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_name_conflicts() {
+        let mut db = Database::new(None);
+        db.insert("mail", "a");
+        db.insert("mail-1", "b");
+        let name = |action| match action {
+            Action::Insert(v) => format!("insert {v}"),
+            Action::Overwrite(v) => format!("overwrite {v}"),
+            Action::Skip => "skip".to_string(),
+        };
+
+        assert_eq!(name(resolve(&db, "web", Conflict::Skip)), "insert web");
+        assert_eq!(name(resolve(&db, "mail", Conflict::Skip)), "skip");
+        assert_eq!(
+            name(resolve(&db, "mail", Conflict::Overwrite)),
+            "overwrite mail"
+        );
+        assert_eq!(
+            name(resolve(&db, "mail", Conflict::Rename)),
+            "insert mail-2"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use super::{Parsed, Record};
+use crate::error::Error;
+use crate::Result;
+
+use serde::Deserialize;
+use std::fs::File;
+use std::path::Path;
+
+#[derive(Deserialize)]
+struct Export {
+    accounts: Vec<Account>,
+}
+
+#[derive(Deserialize)]
+struct Account {
+    vaults: Vec<Vault>,
+}
+
+#[derive(Deserialize)]
+struct Vault {
+    attrs: VaultAttrs,
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct VaultAttrs {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Item {
+    #[serde(default)]
+    state: String,
+    overview: Overview,
+    details: Details,
+}
+
+#[derive(Deserialize)]
+struct Overview {
+    #[serde(default)]
+    title: String,
+    url: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Details {
+    #[serde(default)]
+    login_fields: Vec<LoginField>,
+    notes_plain: Option<String>,
+    password: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LoginField {
+    #[serde(default)]
+    value: String,
+    designation: Option<String>,
+}
+
+/// Read a 1Password `.1pux` archive. Each vault becomes a folder.
+pub fn read_1pux(path: &Path) -> Result<Parsed> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let data = archive
+        .by_name("export.data")
+        .map_err(|_| Error::InvalidImport("missing export.data".into()))?;
+    let export: Export = serde_json::from_reader(data)?;
+    let mut parsed = Parsed::default();
+
+    let vaults = export.accounts.into_iter().flat_map(|v| v.vaults);
+    for vault in vaults {
+        for item in vault.items {
+            let title = item.overview.title;
+            if item.state == "archived" || item.state == "deleted" {
+                parsed.skip(&title, &format!("{} item", item.state));
+                continue;
+            }
+            let details = item.details;
+            let designated = |name: &str| {
+                details
+                    .login_fields
+                    .iter()
+                    .find(|v| v.designation.as_deref() == Some(name))
+                    .map_or("", |v| v.value.as_str())
+            };
+            let password = match details.password.as_deref() {
+                Some(v) => v,
+                None => designated("password"),
+            };
+            let record = Record::new(&vault.attrs.name, &title, password)
+                .field("username", designated("username"))
+                .field("url", item.overview.url.as_deref().unwrap_or(""))
+                .field("notes", details.notes_plain.as_deref().unwrap_or(""))
+                .tags(&item.overview.tags);
+            parsed.push(record);
+        }
+    }
+    Ok(parsed)
+}
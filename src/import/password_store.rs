@@ -0,0 +1,48 @@
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+/// Walk a password-store directory tree and collect every `.gpg` file
+/// along with the entry name it maps to. Hidden files and directories
+/// (`.git`, `.gpg-id`, ...) are ignored.
+pub fn scan(dir: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    let mut found = vec![];
+    scan_dir(dir, dir, &mut found)?;
+    found.sort();
+    Ok(found)
+}
+
+fn scan_dir(
+    root: &Path,
+    dir: &Path,
+    found: &mut Vec<(String, PathBuf)>,
+) -> io::Result<()> {
+    for item in fs::read_dir(dir)? {
+        let item = item?;
+        let path = item.path();
+        let hidden = path
+            .file_name()
+            .is_none_or(|v| v.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if item.file_type()?.is_dir() {
+            scan_dir(root, &path, found)?;
+            continue;
+        }
+        // Symlinked directories are skipped, as they may form a loop
+        if path.is_dir() {
+            continue;
+        }
+        if path.extension().is_none_or(|v| v != "gpg") {
+            continue;
+        }
+        let relative = path.strip_prefix(root).unwrap().with_extension("");
+        let name = relative
+            .components()
+            .map(|v| v.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        found.push((name, path));
+    }
+    Ok(())
+}
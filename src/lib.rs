@@ -0,0 +1,67 @@
+//! An encrypted password store.
+//!
+//! Data file structure
+//! ```text
+//! ───────────────────────────────────────────────────────────────────
+//! "pass"<format version>
+//! <GNUPG private key id to use>
+//! <One-time generated ChaCha20 key><ChaCha20 Nonce>
+//! <key>:<value>
+//! <key>:<value>
+//! ...
+//! ───────────────────────────────────────────────────────────────────
+//! Everything below is encrypted with the last key above it.
+//!  * GNUPG private key id is unencrypted. It is empty if the store
+//!    hides it, and then only kept in the encrypted body
+//!  * ChaCha20 keys are encrypted with the choice of GNUPG's key
+//!  * <key>:<value> pairs are encrypted with ChaCha20
+//!  * the key id and the encrypted ChaCha20 key are each preceded by
+//!    their length: four bytes big-endian since format version 2, two
+//!    bytes in version 1, which has no "pass" marker
+//! ```
+
+mod agent;
+mod attachment;
+mod audit;
+mod chacha_io;
+mod cli;
+mod clipboard;
+mod config;
+mod database;
+mod entry;
+mod error;
+mod expiry;
+mod export;
+mod gpg;
+mod header;
+mod hibp;
+mod import;
+mod memory;
+mod otp;
+mod reference;
+mod sized_io;
+mod skim;
+mod ssh;
+mod store;
+mod template;
+mod timestamp;
+
+pub use entry::Entry;
+pub use error::{Error, Result};
+pub use store::Store;
+
+use config::Config;
+use database::Database;
+use header::Header;
+
+/// Not a stable API: the `pass` binary, and what the integration tests
+/// and fuzz targets need to reach.
+#[doc(hidden)]
+pub mod internal {
+    pub use crate::attachment::Attachment;
+    pub use crate::chacha_io::{ChaReader, ChaWriter};
+    pub use crate::cli::run;
+    pub use crate::database::Database;
+    pub use crate::header::{Header, HEADER_BYTE_LEN};
+    pub use crate::sized_io::{SizedRead, SizedWrite};
+}
@@ -0,0 +1,108 @@
+//! Keeping secrets out of swap. Locking may fail (e.g. over the
+//! RLIMIT_MEMLOCK limit), in which case memory is simply left unlocked.
+//!
+//! `mlock` works on whole pages and locks do not nest, so unlocking one
+//! buffer would unlock every other secret sharing its pages. Each
+//! `Locked` buffer therefore gets pages of its own.
+
+use std::alloc::{self, Layout};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::{fmt, slice};
+use zeroize::Zeroize;
+
+/// A zero-filled byte buffer on its own locked pages. Wiped, unlocked
+/// and freed on drop.
+pub struct Locked {
+    ptr: NonNull<u8>,
+    len: usize,
+    layout: Layout,
+}
+
+// Owns its allocation, like a `Box<[u8]>`
+unsafe impl Send for Locked {}
+unsafe impl Sync for Locked {}
+
+fn page_size() -> usize {
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as usize,
+        _ => 4096,
+    }
+}
+
+impl Locked {
+    pub fn new(len: usize) -> Self {
+        let page = page_size();
+        let size = len.max(1).div_ceil(page) * page;
+        let layout = Layout::from_size_align(size, page).unwrap();
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+        let Some(ptr) = NonNull::new(ptr) else {
+            alloc::handle_alloc_error(layout)
+        };
+        unsafe { libc::mlock(ptr.as_ptr().cast(), size) };
+        Self { ptr, len, layout }
+    }
+}
+
+impl Deref for Locked {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for Locked {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for Locked {
+    fn drop(&mut self) {
+        let size = self.layout.size();
+        unsafe {
+            slice::from_raw_parts_mut(self.ptr.as_ptr(), size).zeroize();
+            libc::munlock(self.ptr.as_ptr().cast(), size);
+            alloc::dealloc(self.ptr.as_ptr(), self.layout);
+        }
+    }
+}
+
+impl fmt::Debug for Locked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Locked({} bytes)", self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffers_do_not_share_pages() {
+        let page = page_size();
+        let buffers: Vec<Locked> = [0, 1, 44, page, page + 1]
+            .iter()
+            .map(|&len| Locked::new(len))
+            .collect();
+        let mut ranges: Vec<_> = buffers
+            .iter()
+            .map(|v| {
+                let start = v.ptr.as_ptr() as usize;
+                assert_eq!(start % page, 0);
+                start..start + v.layout.size()
+            })
+            .collect();
+        ranges.sort_by_key(|v| v.start);
+        assert!(ranges.windows(2).all(|v| v[0].end <= v[1].start));
+    }
+
+    #[test]
+    fn starts_zeroed() {
+        let mut data = Locked::new(100);
+        assert_eq!(data.len(), 100);
+        assert!(data.iter().all(|&v| v == 0));
+        data[99] = 7;
+        assert_eq!(data[99], 7);
+    }
+}
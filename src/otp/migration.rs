@@ -0,0 +1,264 @@
+//! Google Authenticator batch exports: `otpauth-migration://offline?data=`
+//! followed by a base64 encoded protobuf `MigrationPayload`.
+
+use super::{Algorithm, Kind, Otp};
+use crate::{Error, Result};
+
+use base64::Engine;
+use zeroize::Zeroizing;
+
+pub const PREFIX: &str = "otpauth-migration://";
+
+/// Decode every account in a migration URI.
+pub fn decode(uri: &str) -> Result<Vec<Otp>> {
+    let invalid = |msg: &str| Error::InvalidOtp(msg.to_string());
+    let query = uri
+        .trim()
+        .strip_prefix(PREFIX)
+        .and_then(|v| v.split_once('?'))
+        .ok_or_else(|| invalid("not an otpauth-migration:// URI"))?
+        .1;
+    let data = query
+        .split('&')
+        .find_map(|v| v.strip_prefix("data="))
+        .ok_or_else(|| invalid("missing data"))?;
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(Zeroizing::new(super::percent_decode(data)).as_bytes())
+        .map(Zeroizing::new)
+        .map_err(|_| invalid("data is not valid base64"))?;
+
+    let mut accounts = vec![];
+    for (field, value) in Message::new(&data) {
+        if let (1, Value::Bytes(bytes)) = (field?, value) {
+            accounts.push(parameters(bytes)?);
+        }
+    }
+    Ok(accounts)
+}
+
+/// Convert an `OtpParameters` message.
+fn parameters(data: &[u8]) -> Result<Otp> {
+    let mut otp = Otp {
+        kind: Kind::Totp,
+        label: String::new(),
+        issuer: None,
+        secret: Zeroizing::new(vec![]),
+        algorithm: Algorithm::Sha1,
+        digits: 6,
+        period: 30,
+        counter: 0,
+    };
+    let text = |v: &[u8]| String::from_utf8_lossy(v).to_string();
+    for (field, value) in Message::new(data) {
+        match (field?, value) {
+            (1, Value::Bytes(v)) => otp.secret = Zeroizing::new(v.to_vec()),
+            (2, Value::Bytes(v)) => otp.label = text(v),
+            (3, Value::Bytes(v)) if !v.is_empty() => otp.issuer = Some(text(v)),
+            (4, Value::Varint(v)) => {
+                otp.algorithm = match v {
+                    0 | 1 => Algorithm::Sha1,
+                    2 => Algorithm::Sha256,
+                    3 => Algorithm::Sha512,
+                    _ => {
+                        return Err(Error::InvalidOtp(
+                            "unsupported algorithm".into(),
+                        ))
+                    }
+                }
+            }
+            (5, Value::Varint(v)) => otp.digits = if v == 2 { 8 } else { 6 },
+            (6, Value::Varint(v)) => {
+                otp.kind = if v == 1 { Kind::Hotp } else { Kind::Totp }
+            }
+            (7, Value::Varint(v)) => otp.counter = v,
+            _ => {}
+        }
+    }
+    if otp.secret.is_empty() {
+        return Err(Error::InvalidOtp("missing secret".into()));
+    }
+    Ok(otp)
+}
+
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Other,
+}
+
+/// Iterates over the fields of a protobuf message. Only varint and
+/// length-delimited values are read; fixed-width values are skipped.
+struct Message<'a> {
+    data: &'a [u8],
+    failed: bool,
+}
+
+impl<'a> Message<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, failed: false }
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (byte, rest) = self.data.split_first()?;
+            self.data = rest;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.data.len() {
+            return None;
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(taken)
+    }
+
+    fn field(&mut self) -> Option<(u64, Value<'a>)> {
+        let key = self.varint()?;
+        let value = match key & 7 {
+            0 => Value::Varint(self.varint()?),
+            1 => self.take(8).map(|_| Value::Other)?,
+            2 => {
+                let len = self.varint()? as usize;
+                Value::Bytes(self.take(len)?)
+            }
+            5 => self.take(4).map(|_| Value::Other)?,
+            _ => return None,
+        };
+        Some((key >> 3, value))
+    }
+}
+
+impl<'a> Iterator for Message<'a> {
+    type Item = (Result<u64>, Value<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() || self.failed {
+            return None;
+        }
+        match self.field() {
+            Some((field, value)) => Some((Ok(field), value)),
+            None => {
+                self.failed = true;
+                let e = Error::InvalidOtp("malformed migration data".into());
+                Some((Err(e), Value::Other))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut v: u64) -> Vec<u8> {
+        let mut out = vec![];
+        while v >= 0x80 {
+            out.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        out.push(v as u8);
+        out
+    }
+
+    fn bytes(field: u64, data: &[u8]) -> Vec<u8> {
+        [varint(field << 3 | 2), varint(data.len() as u64), data.to_vec()]
+            .concat()
+    }
+
+    fn uri(payload: &[u8]) -> String {
+        let data = base64::engine::general_purpose::STANDARD.encode(payload);
+        format!("{PREFIX}offline?data={}", crate::otp::percent_encode(&data))
+    }
+
+    fn error(uri: &str) -> String {
+        decode(uri).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn decodes_a_known_export() {
+        let uri =
+            "otpauth-migration://offline?data=CjEKCkhlbGxvId6tvu8SGEV4YW1\
+                   wbGU6YWxpY2VAZ29vZ2xlLmNvbRoHRXhhbXBsZTAC";
+        let otp = &decode(uri).unwrap()[0];
+        assert_eq!(otp.kind, Kind::Totp);
+        assert_eq!(&otp.secret[..], b"Hello!\xde\xad\xbe\xef");
+        assert_eq!(otp.label, "Example:alice@google.com");
+        assert_eq!(otp.issuer.as_deref(), Some("Example"));
+        assert_eq!((otp.algorithm, otp.digits), (Algorithm::Sha1, 6));
+    }
+
+    #[test]
+    fn decodes_every_account() {
+        let hotp = [
+            bytes(1, b"12345678901234567890"),
+            bytes(2, b"bob"),
+            vec![0x20, 2, 0x28, 2, 0x30, 1, 0x38],
+            varint(300),
+            // a fixed-width field, which is skipped
+            vec![0x45, 0, 0, 0, 0],
+        ]
+        .concat();
+        let totp = [bytes(1, b"secret"), bytes(2, b"alice")].concat();
+        let payload = [bytes(1, &hotp), bytes(1, &totp), vec![0x10, 1]];
+
+        let accounts = decode(&uri(&payload.concat())).unwrap();
+        assert_eq!(accounts.len(), 2);
+        let (hotp, totp) = (&accounts[0], &accounts[1]);
+        assert_eq!(
+            (hotp.kind, hotp.counter, hotp.digits),
+            (Kind::Hotp, 300, 8)
+        );
+        assert_eq!(hotp.algorithm, Algorithm::Sha256);
+        assert_eq!((totp.kind, totp.label.as_str()), (Kind::Totp, "alice"));
+    }
+
+    #[test]
+    fn rejects_malformed_uris() {
+        assert!(error("otpauth://totp/x?secret=AA").contains("migration"));
+        assert!(error(&format!("{PREFIX}offline")).contains("migration"));
+        assert!(error(&format!("{PREFIX}offline?x=1")).contains("data"));
+        let e = error(&format!("{PREFIX}offline?data=%%%"));
+        assert!(e.contains("base64"));
+    }
+
+    #[test]
+    fn rejects_malformed_payloads() {
+        let account = [bytes(1, b"secret"), bytes(2, b"alice")].concat();
+        let good = bytes(1, &account);
+        // every prefix of a good payload is cut short somewhere
+        for len in 1..good.len() {
+            let e = error(&uri(&good[..len]));
+            assert!(e.contains("malformed migration data"), "{len}: {e}");
+        }
+
+        let too_long_varint = [vec![0x38], vec![0xff; 10], vec![0x01]];
+        let oversized = [vec![0x0a], varint(u64::MAX)];
+        let oversized_inner =
+            bytes(1, &[vec![0x0a], varint(1 << 40), vec![0; 4]].concat());
+        let unknown_wire_type = vec![0x0b];
+        let cases = [
+            bytes(1, &[bytes(1, b"s"), too_long_varint.concat()].concat()),
+            oversized.concat(),
+            oversized_inner,
+            unknown_wire_type,
+            vec![0x0a, 0x80],
+        ];
+        for payload in cases {
+            let e = error(&uri(&payload));
+            assert!(e.contains("malformed migration data"), "{payload:?}");
+        }
+
+        let no_secret = bytes(1, &bytes(2, b"alice"));
+        assert!(error(&uri(&no_secret)).contains("missing secret"));
+        let algorithm = bytes(1, &[bytes(1, b"s"), vec![0x20, 9]].concat());
+        assert!(error(&uri(&algorithm)).contains("unsupported algorithm"));
+    }
+}
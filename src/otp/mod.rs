@@ -0,0 +1,439 @@
+pub mod migration;
+pub mod qr;
+
+use crate::{Error, Result};
+
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use zeroize::Zeroizing;
+
+/// Time-based or counter-based codes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Totp,
+    Hotp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// A one-time code generator parsed from an `otpauth://` URI.
+#[derive(Debug, Clone)]
+pub struct Otp {
+    pub kind: Kind,
+    pub label: String,
+    pub issuer: Option<String>,
+    pub secret: Zeroizing<Vec<u8>>,
+    pub algorithm: Algorithm,
+    pub digits: u32,
+    pub period: u64,
+    pub counter: u64,
+}
+
+impl Otp {
+    /// Parse an `otpauth://totp/...` or `otpauth://hotp/...` URI.
+    pub fn parse(uri: &str) -> Result<Self> {
+        let invalid = |msg: &str| Error::InvalidOtp(msg.to_string());
+        let rest = uri
+            .trim()
+            .strip_prefix("otpauth://")
+            .ok_or_else(|| invalid("not an otpauth:// URI"))?;
+        let (kind, rest) =
+            rest.split_once('/').ok_or_else(|| invalid("missing otp type"))?;
+        let kind = match kind.to_ascii_lowercase().as_str() {
+            "totp" => Kind::Totp,
+            "hotp" => Kind::Hotp,
+            v => return Err(invalid(&format!("unknown otp type {v}"))),
+        };
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        let mut otp = Otp {
+            kind,
+            label: percent_decode(label),
+            issuer: None,
+            secret: Zeroizing::new(vec![]),
+            algorithm: Algorithm::Sha1,
+            digits: 6,
+            period: 30,
+            counter: 0,
+        };
+        let mut has_counter = false;
+        for pair in query.split('&').filter(|v| !v.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = Zeroizing::new(percent_decode(value));
+            let number = || value.parse::<u64>().map_err(|_| invalid(key));
+            match key.to_ascii_lowercase().as_str() {
+                "secret" => otp.secret = base32_decode(&value)?,
+                "issuer" => otp.issuer = Some(value.to_string()),
+                "algorithm" => otp.algorithm = algorithm(&value)?,
+                "digits" => {
+                    otp.digits =
+                        u32::try_from(number()?).map_err(|_| invalid(key))?
+                }
+                "period" => otp.period = number()?,
+                "counter" => {
+                    otp.counter = number()?;
+                    has_counter = true;
+                }
+                _ => {}
+            }
+        }
+        if otp.secret.is_empty() {
+            return Err(invalid("missing secret"));
+        }
+        if !(6..=10).contains(&otp.digits) {
+            return Err(invalid("digits must be between 6 and 10"));
+        }
+        if otp.period == 0 {
+            return Err(invalid("period must be positive"));
+        }
+        if kind == Kind::Hotp && !has_counter {
+            return Err(invalid("missing counter"));
+        }
+        Ok(otp)
+    }
+
+    /// Serialize back into an `otpauth://` URI.
+    pub fn to_uri(&self) -> Zeroizing<String> {
+        let kind = match self.kind {
+            Kind::Totp => "totp",
+            Kind::Hotp => "hotp",
+        };
+        let algorithm = match self.algorithm {
+            Algorithm::Sha1 => "SHA1",
+            Algorithm::Sha256 => "SHA256",
+            Algorithm::Sha512 => "SHA512",
+        };
+        let secret = Zeroizing::new(base32_encode(&self.secret));
+        let mut uri = Zeroizing::new(format!(
+            "otpauth://{kind}/{}?secret={}&algorithm={algorithm}&digits={}",
+            percent_encode(&self.label),
+            *secret,
+            self.digits,
+        ));
+        if let Some(issuer) = &self.issuer {
+            uri.push_str(&format!("&issuer={}", percent_encode(issuer)));
+        }
+        match self.kind {
+            Kind::Totp => uri.push_str(&format!("&period={}", self.period)),
+            Kind::Hotp => uri.push_str(&format!("&counter={}", self.counter)),
+        }
+        uri
+    }
+
+    /// A name to store this account under: `<issuer>/<account>`.
+    pub fn suggested_name(&self) -> String {
+        let (prefix, account) = match self.label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer), account),
+            None => (None, self.label.as_str()),
+        };
+        let issuer = self.issuer.as_deref().or(prefix);
+        [issuer.unwrap_or(""), account]
+            .iter()
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// A TOTP generator with default settings for a bare base32 secret.
+    pub fn from_secret(secret: &str) -> Result<Self> {
+        Self::parse(&Zeroizing::new(format!("otpauth://totp/?secret={secret}")))
+    }
+
+    /// The code for the current time (TOTP) or the current counter (HOTP).
+    pub fn code(&self, now: u64) -> String {
+        match self.kind {
+            Kind::Totp => self.hotp(now / self.period),
+            Kind::Hotp => self.hotp(self.counter),
+        }
+    }
+
+    /// Seconds until the current TOTP code changes
+    pub fn remaining(&self, now: u64) -> u64 {
+        self.period - now % self.period
+    }
+
+    /// RFC 4226 HOTP value for counter `c`.
+    fn hotp(&self, c: u64) -> String {
+        let msg = c.to_be_bytes();
+        let hash = match self.algorithm {
+            Algorithm::Sha1 => mac::<Hmac<Sha1>>(&self.secret, &msg),
+            Algorithm::Sha256 => mac::<Hmac<Sha256>>(&self.secret, &msg),
+            Algorithm::Sha512 => mac::<Hmac<Sha512>>(&self.secret, &msg),
+        };
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let bytes: [u8; 4] = hash[offset..offset + 4].try_into().unwrap();
+        let binary = (u32::from_be_bytes(bytes) & 0x7fff_ffff) as u64;
+        let code = binary % 10u64.pow(self.digits);
+        format!("{code:0width$}", width = self.digits as usize)
+    }
+}
+
+fn mac<M: Mac + hmac::digest::KeyInit>(key: &[u8], msg: &[u8]) -> Vec<u8> {
+    let mut mac = <M as Mac>::new_from_slice(key).unwrap();
+    mac.update(msg);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn algorithm(name: &str) -> Result<Algorithm> {
+    match name.to_ascii_uppercase().as_str() {
+        "SHA1" => Ok(Algorithm::Sha1),
+        "SHA256" => Ok(Algorithm::Sha256),
+        "SHA512" => Ok(Algorithm::Sha512),
+        v => Err(Error::InvalidOtp(format!("unknown algorithm {v}"))),
+    }
+}
+
+/// Read every account from the QR codes in an image. Codes may hold an
+/// `otpauth://` URI or a Google Authenticator `otpauth-migration://` batch.
+pub fn read_image(path: &std::path::Path) -> Result<Vec<Otp>> {
+    let mut accounts = vec![];
+    for content in qr::decode(path)? {
+        if content.starts_with(migration::PREFIX) {
+            accounts.extend(migration::decode(&content)?);
+        } else {
+            accounts.push(Otp::parse(&content)?);
+        }
+    }
+    Ok(accounts)
+}
+
+/// Find the otp settings of a stored value: an `otpauth://` URI on any
+/// line, or a bare secret in a `totp:` field.
+pub fn find(value: &str) -> Option<Result<Otp>> {
+    if let Some(uri) = find_uri(value) {
+        return Some(Otp::parse(uri));
+    }
+    let secret = value.lines().find_map(|v| v.strip_prefix("totp: "))?;
+    Some(Otp::from_secret(secret.trim()))
+}
+
+/// The first `otpauth://` URI in a stored value.
+pub fn find_uri(value: &str) -> Option<&str> {
+    value.lines().find_map(|line| {
+        let start = line.find("otpauth://")?;
+        line[start..].split_whitespace().next()
+    })
+}
+
+/// Replace the `counter` parameter of an `otpauth://` URI.
+pub fn with_counter(uri: &str, counter: u64) -> String {
+    let (base, query) = uri.split_once('?').unwrap_or((uri, ""));
+    let query: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((k, _)) if k.eq_ignore_ascii_case("counter") => {
+                format!("{k}={counter}")
+            }
+            _ => pair.to_string(),
+        })
+        .collect();
+    format!("{base}?{}", query.join("&"))
+}
+
+/// Decode RFC 4648 base32, ignoring case, spaces and padding.
+pub fn base32_decode(input: &str) -> Result<Zeroizing<Vec<u8>>> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut out = Zeroizing::new(vec![]);
+    let (mut buffer, mut bits) = (0u64, 0);
+    for c in input.bytes().filter(|v| !matches!(v, b' ' | b'-' | b'=')) {
+        let c = c.to_ascii_uppercase();
+        let Some(v) = ALPHABET.iter().position(|a| *a == c) else {
+            return Err(Error::InvalidOtp("invalid base32 secret".into()));
+        };
+        buffer = (buffer << 5) | v as u64;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(out)
+}
+
+/// Encode bytes as unpadded RFC 4648 base32.
+pub fn base32_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut out = String::new();
+    let (mut buffer, mut bits) = (0u64, 0);
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u64;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        out.push(ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+    out
+}
+
+/// Escape everything but unreserved characters in a URI component.
+pub fn percent_encode(input: &str) -> String {
+    input
+        .bytes()
+        .map(|v| match v {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~' => (v as char).to_string(),
+            v => format!("%{v:02X}"),
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes in a URI component.
+pub fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|v| {
+            u8::from_str_radix(std::str::from_utf8(v).ok()?, 16).ok()
+        });
+        match (bytes[i], hex) {
+            (b'%', Some(v)) => {
+                out.push(v);
+                i += 3;
+            }
+            (v, _) => {
+                out.push(v);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(kind: &str, seed: &[u8], params: &str) -> Otp {
+        let secret = base32_encode(seed);
+        Otp::parse(&format!("otpauth://{kind}/t?secret={secret}&{params}"))
+            .unwrap()
+    }
+
+    /// RFC 4226, Appendix D
+    #[test]
+    fn hotp_vectors() {
+        let codes = [
+            "755224", "287082", "359152", "969429", "338314", "254676",
+            "287922", "162583", "399871", "520489",
+        ];
+        for (counter, code) in codes.iter().enumerate() {
+            let params = format!("counter={counter}");
+            let otp = parse("hotp", b"12345678901234567890", &params);
+            assert_eq!(otp.code(0), *code, "counter {counter}");
+        }
+    }
+
+    /// RFC 6238, Appendix B
+    #[test]
+    fn totp_vectors() {
+        let sha1 = b"12345678901234567890".as_slice();
+        let sha256 = b"12345678901234567890123456789012".as_slice();
+        let sha512 = b"1234567890123456789012345678901234567890\
+                       123456789012345678901234"
+            .as_slice();
+        let vectors = [
+            (59, ["94287082", "46119246", "90693936"]),
+            (1111111109, ["07081804", "68084774", "25091201"]),
+            (1111111111, ["14050471", "67062674", "99943326"]),
+            (1234567890, ["89005924", "91819424", "93441116"]),
+            (2000000000, ["69279037", "90698825", "38618901"]),
+            (20000000000, ["65353130", "77737706", "47863826"]),
+        ];
+        let algorithms =
+            [("SHA1", sha1), ("SHA256", sha256), ("SHA512", sha512)];
+        for (time, codes) in vectors {
+            for ((name, seed), code) in algorithms.iter().zip(codes) {
+                let params = format!("algorithm={name}&digits=8");
+                let otp = parse("totp", seed, &params);
+                assert_eq!(otp.code(time), code, "{name} at {time}");
+
+                // the same code, truncated to the default 6 digits
+                let otp = parse("totp", seed, &format!("algorithm={name}"));
+                assert_eq!(otp.code(time), code[2..], "{name} at {time}");
+            }
+        }
+    }
+
+    #[test]
+    fn uri_round_trip() {
+        let uri = "otpauth://hotp/Example:alice%40example.com\
+                   ?secret=JBSWY3DPEHPK3PXP&issuer=Example&counter=7\
+                   &digits=8&algorithm=sha256";
+        let otp = Otp::parse(uri).unwrap();
+        assert_eq!(otp.label, "Example:alice@example.com");
+        assert_eq!(otp.suggested_name(), "Example/alice@example.com");
+        let again = Otp::parse(&otp.to_uri()).unwrap();
+        assert_eq!(again.code(0), otp.code(0));
+        assert_eq!((again.counter, again.digits), (7, 8));
+        assert_eq!(again.algorithm, Algorithm::Sha256);
+    }
+
+    #[test]
+    fn rejects_malformed_uris() {
+        let secret = "secret=JBSWY3DPEHPK3PXP";
+        let cases = [
+            ("https://example.com", "not an otpauth:// URI"),
+            ("otpauth://totp", "missing otp type"),
+            (&format!("otpauth://motp/x?{secret}"), "unknown otp type"),
+            ("otpauth://totp/x?issuer=y", "missing secret"),
+            ("otpauth://totp/x?secret=", "missing secret"),
+            ("otpauth://totp/x?secret=AB1C", "invalid base32"),
+            (&format!("otpauth://totp/x?{secret}&digits=0"), "digits"),
+            (&format!("otpauth://totp/x?{secret}&digits=5"), "digits"),
+            (&format!("otpauth://totp/x?{secret}&digits=11"), "digits"),
+            // 2^32 + 6, which must not wrap around to 6
+            (&format!("otpauth://totp/x?{secret}&digits=4294967302"), "digits"),
+            (&format!("otpauth://totp/x?{secret}&digits=six"), "digits"),
+            (&format!("otpauth://totp/x?{secret}&period=0"), "period"),
+            (&format!("otpauth://totp/x?{secret}&period=-5"), "period"),
+            (
+                &format!("otpauth://totp/x?{secret}&algorithm=md5"),
+                "unknown algorithm",
+            ),
+            (&format!("otpauth://hotp/x?{secret}"), "missing counter"),
+            (&format!("otpauth://hotp/x?{secret}&counter=x"), "counter"),
+        ];
+        for (uri, expected) in cases {
+            let e = Otp::parse(uri).unwrap_err();
+            assert!(e.to_string().contains(expected), "{uri}: {e}");
+        }
+    }
+
+    #[test]
+    fn base32() {
+        let data = b"12345678901234567890";
+        let encoded = base32_encode(data);
+        assert_eq!(encoded, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(*base32_decode(&encoded).unwrap(), data);
+        assert_eq!(
+            *base32_decode("gezd gnbv-gy3t qojq gezd gnbv gy3t qojq===")
+                .unwrap(),
+            data
+        );
+        assert_eq!(*base32_decode("MZXW6===").unwrap(), b"foo");
+        assert!(base32_decode("").unwrap().is_empty());
+
+        for input in ["0", "1", "8", "MZXW6!", "MZ/W6", "ÄA"] {
+            assert!(base32_decode(input).is_err(), "{input}");
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{Error, Result};
+
+use std::path::Path;
+
+/// Decode every QR code found in a PNG or JPEG image.
+pub fn decode(path: &Path) -> Result<Vec<String>> {
+    let image = image::open(path)
+        .map_err(|e| Error::InvalidOtp(format!("cannot read image: {e}")))?
+        .to_luma8();
+    let (w, h) = (image.width() as usize, image.height() as usize);
+    let mut prepared =
+        rqrr::PreparedImage::prepare_from_greyscale(w, h, |x, y| {
+            image.get_pixel(x as u32, y as u32).0[0]
+        });
+    let contents: Vec<String> = prepared
+        .detect_grids()
+        .iter()
+        .filter_map(|grid| grid.decode().ok())
+        .map(|(_, content)| content)
+        .collect();
+    if contents.is_empty() {
+        return Err(Error::InvalidOtp("no QR code found in image".into()));
+    }
+    Ok(contents)
+}
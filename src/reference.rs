@@ -0,0 +1,123 @@
+//! References to a value in the store: `<name>` for an entry's password,
+//! or `<name>:<field>` for one of its metadata fields.
+
+use crate::{entry, Error, Result, Store};
+
+use zeroize::Zeroizing;
+
+/// Look up a reference. A name that contains `:` is tried as a whole
+/// before being split into a name and a field.
+pub fn resolve(store: &Store, reference: &str) -> Result<Zeroizing<String>> {
+    if let Some(value) = store.get(reference) {
+        return Ok(Zeroizing::new(entry::password(value).to_string()));
+    }
+    match reference.rsplit_once(':') {
+        Some((name, field)) => lookup(store, name, field),
+        None => Err(Error::NameNotFound(reference.to_string())),
+    }
+}
+
+/// Look up a field of an entry.
+pub fn lookup(
+    store: &Store,
+    name: &str,
+    field: &str,
+) -> Result<Zeroizing<String>> {
+    let value =
+        store.get(name).ok_or_else(|| Error::NameNotFound(name.to_string()))?;
+    entry::field(value, field).map(Zeroizing::new).ok_or_else(|| {
+        Error::FieldNotFound(name.to_string(), field.to_string())
+    })
+}
+
+/// Parse `VAR=<reference>` lines. Blank lines and lines starting with
+/// `#` are ignored.
+pub fn parse_env_file(text: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        match parse_env(line) {
+            Ok(v) => vars.push(v),
+            Err(e) => {
+                let msg = format!("line {}: {e}", i + 1);
+                return Err(Error::InvalidReference(msg));
+            }
+        }
+    }
+    Ok(vars)
+}
+
+/// Parse a single `VAR=<reference>` pair.
+pub fn parse_env(arg: &str) -> std::result::Result<(String, String), String> {
+    let (var, reference) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected VAR=<name>, got `{arg}`"))?;
+    let var = var.trim();
+    let valid = var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !var.starts_with(|c: char| c.is_ascii_digit());
+    if var.is_empty() || !valid {
+        return Err(format!("invalid variable name `{var}`"));
+    }
+    let reference = reference.trim().trim_matches('"');
+    Ok((var.to_string(), reference.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> Store {
+        let mut store = Store::create("/nonexistent", "key");
+        store.insert("db", "pw\nuser: bob").unwrap();
+        store.insert("host:8080", "colon\nuser: root").unwrap();
+        store
+    }
+
+    #[test]
+    fn resolves_passwords_and_fields() {
+        let store = store();
+        assert_eq!(*resolve(&store, "db").unwrap(), "pw");
+        assert_eq!(*resolve(&store, "db:user").unwrap(), "bob");
+        assert_eq!(*resolve(&store, "db:password").unwrap(), "pw");
+    }
+
+    #[test]
+    fn names_may_contain_colons() {
+        let store = store();
+        assert_eq!(*resolve(&store, "host:8080").unwrap(), "colon");
+        assert_eq!(*resolve(&store, "host:8080:user").unwrap(), "root");
+    }
+
+    #[test]
+    fn missing_entries_and_fields_are_errors() {
+        let store = store();
+        let e = resolve(&store, "web").unwrap_err();
+        assert!(matches!(e, Error::NameNotFound(v) if v == "web"));
+        let e = resolve(&store, "web:user").unwrap_err();
+        assert!(matches!(e, Error::NameNotFound(v) if v == "web"));
+        let e = resolve(&store, "db:url").unwrap_err();
+        let Error::FieldNotFound(name, field) = e else { panic!("{e}") };
+        assert_eq!((name.as_str(), field.as_str()), ("db", "url"));
+    }
+
+    #[test]
+    fn parses_env_files() {
+        let text = "# comment\n\nDB_PASS=db\nexport DB_USER = \"db:user\"\n";
+        assert_eq!(
+            parse_env_file(text).unwrap(),
+            [
+                ("DB_PASS".to_string(), "db".to_string()),
+                ("DB_USER".to_string(), "db:user".to_string()),
+            ]
+        );
+        for line in ["DB", "1DB=db", "DB-PASS=db", "=db"] {
+            assert!(parse_env(line).is_err(), "{line}");
+        }
+        let e = parse_env_file("A=a\nnope").unwrap_err();
+        assert!(e.to_string().contains("line 2"));
+    }
+}
@@ -1,21 +1,67 @@
-use std::io;
+use std::fmt;
+use std::io::{self, Read};
+
+/// Largest frame `sized_read_u32` accepts unless told otherwise: 16 MiB
+pub const MAX_FRAME: usize = 16 << 20;
+
+/// A frame claimed to be longer than the reader allows. Returned inside
+/// an `io::Error` of kind `InvalidData`.
+#[derive(Debug)]
+pub struct FrameTooLarge {
+    pub len: u64,
+    pub limit: u64,
+}
+
+impl fmt::Display for FrameTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "frame of {} bytes exceeds the {} byte limit",
+            self.len, self.limit
+        )
+    }
+}
+
+impl std::error::Error for FrameTooLarge {}
 
 pub trait SizedRead {
     /// use the next two bytes as the length of the next parts to read
     /// size limit is u16::MAX
     fn sized_read(&mut self) -> Result<Vec<u8>, io::Error>;
+
+    /// use the next four bytes (big-endian) as the length of the next
+    /// parts to read. Lengths over `limit` are rejected before anything
+    /// is allocated, and memory is only allocated as data arrives.
+    fn sized_read_u32(&mut self, limit: usize) -> Result<Vec<u8>, io::Error>;
 }
 
 impl<R: io::Read> SizedRead for R {
     fn sized_read(&mut self) -> Result<Vec<u8>, io::Error> {
         let mut len_buffer = [0u8; 2];
-        self.read(&mut len_buffer)?;
+        self.read_exact(&mut len_buffer)?;
 
         // parse the length from the next two bytes
         let len = len_buffer[0] as usize * 256 + len_buffer[1] as usize;
 
         let mut buffer = vec![0u8; len];
-        self.read(&mut buffer)?;
+        self.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    fn sized_read_u32(&mut self, limit: usize) -> Result<Vec<u8>, io::Error> {
+        let mut len_buffer = [0u8; 4];
+        self.read_exact(&mut len_buffer)?;
+        let len = u32::from_be_bytes(len_buffer) as usize;
+        if len > limit {
+            let e = FrameTooLarge { len: len as u64, limit: limit as u64 };
+            return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+
+        let mut buffer = vec![];
+        self.take(len as u64).read_to_end(&mut buffer)?;
+        if buffer.len() < len {
+            Err(io::ErrorKind::UnexpectedEof)?
+        }
         Ok(buffer)
     }
 }
@@ -24,6 +70,10 @@ pub trait SizedWrite {
     /// use the next two bytes as the length of the next parts to write
     /// size limit is u16::MAX
     fn sized_write(&mut self, data: &[u8]) -> Result<(), io::Error>;
+
+    /// use the next four bytes (big-endian) as the length of the next
+    /// parts to write. size limit is u32::MAX
+    fn sized_write_u32(&mut self, data: &[u8]) -> Result<(), io::Error>;
 }
 
 impl<W: io::Write> SizedWrite for W {
@@ -32,12 +82,17 @@ impl<W: io::Write> SizedWrite for W {
             Err(io::ErrorKind::InvalidInput)?
         }
 
-        let mut sized = Vec::with_capacity(data.len() + 2);
-
-        sized.push((data.len() / 256) as u8); // upper
-        sized.push((data.len() % 256) as u8); // lower
-        sized.extend_from_slice(&data);
+        // written separately so that secrets are not copied
+        let len = [(data.len() / 256) as u8, (data.len() % 256) as u8];
+        self.write_all(&len)?;
+        self.write_all(data)
+    }
 
-        self.write_all(&sized)
+    fn sized_write_u32(&mut self, data: &[u8]) -> Result<(), io::Error> {
+        let Ok(len) = u32::try_from(data.len()) else {
+            Err(io::ErrorKind::InvalidInput)?
+        };
+        self.write_all(&len.to_be_bytes())?;
+        self.write_all(data)
     }
 }
@@ -1,11 +1,10 @@
-use std::io::Cursor;
-
+use crate::cli::config;
 use skim::prelude::*;
 
 struct Name(String);
 
 impl SkimItem for Name {
-    fn text(&self) -> Cow<str> {
+    fn text(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.0)
     }
     fn preview(&self, _context: PreviewContext) -> ItemPreview {
@@ -13,11 +12,11 @@ impl SkimItem for Name {
     }
 }
 
-/// Default options for skim
-fn options<'a>() -> Option<SkimOptions<'a>> {
+/// Options for skim, with the layout from the config
+fn options(height: &str) -> Option<SkimOptions<'_>> {
     SkimOptionsBuilder::default()
-        .height(Some("7"))
-        .reverse(true)
+        .height(Some(height))
+        .reverse(config().picker_reverse())
         .color(Some("hl:-1"))
         .no_mouse(true)
         .build()
@@ -28,11 +27,14 @@ fn options<'a>() -> Option<SkimOptions<'a>> {
 pub fn select_one(mut choices: Vec<String>) -> Option<String> {
     choices.sort();
 
-    let input = choices.join("\n");
-    let item_reader = SkimItemReader::default();
-    let items = item_reader.of_bufread(Cursor::new(input));
+    let (sender, items): (SkimItemSender, SkimItemReceiver) = unbounded();
+    for choice in choices {
+        sender.send(Arc::new(Name(choice))).ok()?;
+    }
+    drop(sender);
 
-    Skim::run_with(&options()?, Some(items))
+    let height = config().picker_height();
+    Skim::run_with(&options(&height)?, Some(items))
         .and_then(|mut out| match out.final_key {
             Key::ESC | Key::Ctrl('c') => None,
             _ => out.selected_items.pop(),
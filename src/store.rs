@@ -0,0 +1,119 @@
+use crate::database::Database;
+use crate::entry::{self, Entry};
+use crate::{Error, Result};
+
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// A password store on disk. This is the entry point for tools that
+/// embed the store: open it, work with its entries, then `save` it.
+#[derive(Debug)]
+pub struct Store {
+    path: PathBuf,
+    db: Database,
+}
+
+impl Store {
+    /// Open the store at the default location.
+    pub fn open() -> Result<Self> {
+        Self::open_at(Database::path()?)
+    }
+
+    /// Open the store at `path`, decrypting it with GPG.
+    pub fn open_at<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let db = Database::read_from_file(&path)?;
+        Ok(Self { path, db })
+    }
+
+    /// A new, empty store that will be written to `path`, encrypted for
+    /// `gpg_id`. Nothing is written until `save` is called.
+    pub fn create<P: AsRef<Path>>(path: P, gpg_id: &str) -> Self {
+        let path = path.as_ref().to_path_buf();
+        Self { path, db: Database::new(Some(gpg_id.to_string())) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn has_name(&self, name: &str) -> bool {
+        self.db.has_name(name)
+    }
+
+    /// An entry along with its history and attachments.
+    pub fn entry(&self, name: &str) -> Option<&Entry> {
+        self.db.entry(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.db.get(name).map(|v| v.as_str())
+    }
+
+    /// All entry names, sorted.
+    pub fn list(&self) -> Vec<String> {
+        let mut names = self.db.list_all();
+        names.sort();
+        names
+    }
+
+    /// Names of the entries carrying every one of `tags`, sorted.
+    pub fn list_tagged<S: AsRef<str>>(&self, tags: &[S]) -> Vec<String> {
+        let mut names = self.list();
+        names.retain(|name| {
+            let found = entry::tags(self.db.get_unchecked(name));
+            tags.iter().all(|tag| found.iter().any(|v| v == tag.as_ref()))
+        });
+        names
+    }
+
+    /// Add a new entry. Fails if the name is taken.
+    pub fn insert(&mut self, name: &str, value: &str) -> Result<()> {
+        if self.db.has_name(name) {
+            return Err(Error::NameExists(name.to_string()));
+        }
+        self.db.insert(name, value);
+        Ok(())
+    }
+
+    /// Change the value of an existing entry, keeping the old value in
+    /// its history.
+    pub fn update(&mut self, name: &str, value: &str) -> Result<()> {
+        if !self.db.has_name(name) {
+            return Err(Error::NameNotFound(name.to_string()));
+        }
+        self.db.update(name, value);
+        Ok(())
+    }
+
+    /// Change the metadata of an existing entry, such as its tags or OTP
+    /// counter. Not kept in its history, and not counted as a change of
+    /// its password.
+    pub fn amend(&mut self, name: &str, value: &str) -> Result<()> {
+        if !self.db.amend(name, value) {
+            return Err(Error::NameNotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Remove an entry, moving it to the trash. Returns its value.
+    pub fn remove(&mut self, name: &str) -> Result<Zeroizing<String>> {
+        self.db
+            .remove(name)
+            .ok_or_else(|| Error::NameNotFound(name.to_string()))
+    }
+
+    /// Encrypt and write the store back to its path.
+    pub fn save(&self) -> Result<()> {
+        self.db.write_to_file(&self.path)
+    }
+
+    /// Lower level operations: history, trash, key ids, ...
+    pub(crate) fn db(&self) -> &Database {
+        &self.db
+    }
+
+    pub(crate) fn db_mut(&mut self) -> &mut Database {
+        &mut self.db
+    }
+}
@@ -0,0 +1,137 @@
+//! Templates with secrets substituted in. A placeholder looks like
+//! `{{ pass "db/prod" }}` for an entry's password, or
+//! `{{ pass "db/prod" field="username" }}` for one of its fields.
+//! Other `{{ ... }}` blocks are left untouched.
+
+use crate::{reference, Error, Result, Store};
+
+use zeroize::Zeroizing;
+
+/// Replace every placeholder in `template`. Fails if any placeholder is
+/// malformed or refers to a missing entry or field, listing all of them.
+pub fn render(store: &Store, template: &str) -> Result<Zeroizing<String>> {
+    let mut out = Zeroizing::new(String::with_capacity(template.len()));
+    let mut errors = vec![];
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let block = &rest[start..start + len + 2];
+        let line = template[..template.len() - rest.len() + start]
+            .matches('\n')
+            .count()
+            + 1;
+        rest = &rest[start + len + 2..];
+
+        let inner = block[2..block.len() - 2].trim();
+        let Some(args) = inner.strip_prefix("pass") else {
+            out.push_str(block);
+            continue;
+        };
+        if !args.is_empty() && !args.starts_with(char::is_whitespace) {
+            out.push_str(block);
+            continue;
+        }
+        let value = parse(args).and_then(|(name, field)| {
+            let field = field.as_deref().unwrap_or("password");
+            reference::lookup(store, &name, field).map_err(|e| e.to_string())
+        });
+        match value {
+            Ok(v) => out.push_str(&v),
+            Err(e) => errors.push(format!("line {line}: {e}")),
+        }
+    }
+    out.push_str(rest);
+
+    match errors.is_empty() {
+        true => Ok(out),
+        false => Err(Error::InvalidTemplate(errors.join("\n"))),
+    }
+}
+
+/// Parse the arguments of a placeholder: a quoted name, then an optional
+/// `field="..."`.
+fn parse(args: &str) -> std::result::Result<(String, Option<String>), String> {
+    let (name, rest) = quoted(args.trim_start())
+        .ok_or_else(|| "expected a quoted name".to_string())?;
+    let rest = rest.trim();
+    if rest.is_empty() {
+        return Ok((name, None));
+    }
+    let value = rest
+        .strip_prefix("field")
+        .map(str::trim_start)
+        .and_then(|v| v.strip_prefix('='))
+        .and_then(|v| quoted(v.trim_start()))
+        .filter(|(_, rest)| rest.trim().is_empty())
+        .ok_or_else(|| format!("unexpected `{rest}`"))?;
+    Ok((name, Some(value.0)))
+}
+
+/// Read a double-quoted string with `\"` and `\\` escapes, returning it
+/// along with the remaining input.
+fn quoted(input: &str) -> Option<(String, &str)> {
+    let mut chars = input.strip_prefix('"')?.char_indices();
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &input[i + 2..])),
+            '\\' => value.push(chars.next()?.1),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> Store {
+        let mut store = Store::create("/nonexistent", "key");
+        store.insert("db/prod", "pw\nuser: bob").unwrap();
+        store.insert("host:8080", "colon").unwrap();
+        store.insert("quote\"d", "escaped").unwrap();
+        store
+    }
+
+    #[test]
+    fn renders_passwords_and_fields() {
+        let template = "user={{ pass \"db/prod\" field=\"user\" }}\n\
+                        pass={{pass \"db/prod\"}}\n\
+                        other={{ pass \"host:8080\" }} {{pass \"quote\\\"d\"}}";
+        assert_eq!(
+            *render(&store(), template).unwrap(),
+            "user=bob\npass=pw\nother=colon escaped"
+        );
+    }
+
+    #[test]
+    fn leaves_other_blocks_alone() {
+        let template = "{{ name }} {{passwd}} {{ pass \"db/prod\" }} {{";
+        assert_eq!(
+            *render(&store(), template).unwrap(),
+            "{{ name }} {{passwd}} pw {{"
+        );
+    }
+
+    #[test]
+    fn fails_loudly() {
+        let template = "{{ pass \"web\" }}\n\
+                        {{ pass \"db/prod\" field=\"url\" }}\n\
+                        {{ pass db/prod }}\n\
+                        {{ pass \"db/prod\" user }}\n\
+                        {{ pass \"host:8080\" }}";
+        let e = render(&store(), template).unwrap_err().to_string();
+        let lines: Vec<_> = e.lines().skip(1).collect();
+        assert_eq!(lines.len(), 4, "{e}");
+        assert!(lines[0].starts_with("line 1: No entry named [web]"));
+        assert!(lines[1].starts_with("line 2: ") && lines[1].contains("url"));
+        assert_eq!(lines[2], "line 3: expected a quoted name");
+        assert_eq!(lines[3], "line 4: unexpected `user`");
+    }
+}
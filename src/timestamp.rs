@@ -0,0 +1,99 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since the unix epoch
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Format a unix timestamp as `YYYY-MM-DD HH:MM` (UTC).
+/// A zero timestamp is used for entries written before timestamps were
+/// recorded.
+pub fn format(secs: u64) -> String {
+    if secs == 0 {
+        return "unknown".to_string();
+    }
+    let (days, rem) = (secs / 86400, secs % 86400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        rem / 3600,
+        rem % 3600 / 60
+    )
+}
+
+/// Format a unix timestamp as `YYYY-MM-DD` (UTC).
+pub fn format_date(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Parse a `YYYY-MM-DD` date (UTC) into a unix timestamp.
+pub fn parse_date(date: &str) -> Option<u64> {
+    let mut parts = date.trim().splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    // reject days past the end of the month, e.g. 02-30
+    if civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+    u64::try_from(days).ok().map(|v| v * 86400)
+}
+
+/// Days since 1970-01-01 to a (year, month, day) triple.
+/// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+/// A (year, month, day) triple to days since 1970-01-01.
+/// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - (month <= 2) as i64;
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil_days_round_trip() {
+        for days in [-719468, -1, 0, 1, 59, 365, 11016, 19723, 2932896] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(parse_date("2024-02-29"), Some(1709164800));
+        assert_eq!(format_date(1709164800), "2024-02-29");
+        assert_eq!(format(1709164800 + 3723), "2024-02-29 01:02");
+        assert_eq!(format(0), "unknown");
+        for bad in ["2023-02-29", "2024-13-01", "2024-00-10", "1969-12-31", "x"]
+        {
+            assert_eq!(parse_date(bad), None, "{bad}");
+        }
+    }
+}
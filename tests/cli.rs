@@ -0,0 +1,435 @@
+//! End-to-end tests of the `pass` binary, run against a fake gpg in a
+//! temporary directory.
+
+mod common;
+
+use common::{Sandbox, KEY};
+use predicates::prelude::*;
+use predicates::str::contains;
+use std::fs;
+
+#[test]
+fn init_creates_a_private_store() {
+    let sandbox = Sandbox::init();
+    assert!(sandbox.store_file().is_file());
+
+    use std::os::unix::fs::PermissionsExt;
+    let dir = fs::metadata(sandbox.path("store")).unwrap();
+    assert_eq!(dir.permissions().mode() & 0o777, 0o700);
+
+    sandbox
+        .pass()
+        .args(["init", "other@example.com"])
+        .assert()
+        .stdout(contains("already has an owner id"));
+}
+
+#[test]
+fn init_rejects_unknown_keys() {
+    let sandbox = Sandbox::new();
+    sandbox
+        .pass()
+        .args(["init", "nobody@example.com"])
+        .assert()
+        .stdout(contains("Invalid key id"));
+    assert!(!sandbox.store_file().exists());
+}
+
+#[test]
+fn missing_store_is_reported() {
+    Sandbox::new()
+        .pass()
+        .arg("ls")
+        .assert()
+        .stderr(contains("Database not found"));
+}
+
+#[test]
+fn insert_and_show() {
+    let sandbox = Sandbox::init();
+    sandbox.insert("web/github", "hunter2");
+    sandbox.pass().arg("web/github").assert().stdout("hunter2\n");
+    sandbox.pass().args(["show", "web/github"]).assert().stdout("hunter2\n");
+    sandbox
+        .pass()
+        .arg("web/gitlab")
+        .assert()
+        .stdout(contains("No password found for [web/gitlab]"));
+}
+
+#[test]
+fn insert_generated() {
+    let sandbox = Sandbox::init();
+    sandbox
+        .pass()
+        .args(["insert", "-g", "-l", "40", "bank"])
+        .assert()
+        .success();
+    let out = sandbox.pass().arg("bank").output().unwrap();
+    let password = String::from_utf8(out.stdout).unwrap();
+    assert_eq!(password.trim_end().len(), 40);
+}
+
+#[test]
+fn ls_move_and_remove() {
+    let sandbox = Sandbox::init();
+    sandbox.insert("mail", "a");
+    sandbox.insert("web/github", "b");
+    sandbox.pass().arg("ls").assert().stdout("mail\nweb/github\n");
+
+    sandbox.pass().args(["move", "mail", "email"]).assert().success();
+    sandbox.pass().arg("ls").assert().stdout("email\nweb/github\n");
+
+    sandbox.pass().args(["remove", "email"]).assert().success();
+    sandbox.pass().arg("ls").assert().stdout("web/github\n");
+    sandbox.pass().arg("trash").assert().stdout(contains("email"));
+
+    sandbox.pass().args(["trash", "restore", "email"]).assert().success();
+    sandbox.pass().arg("email").assert().stdout("a\n");
+}
+
+#[test]
+fn edit_keeps_history() {
+    let sandbox = Sandbox::init();
+    sandbox.insert("mail", "first");
+    sandbox.edit("mail", "second\nuser: bob\n");
+    sandbox
+        .pass()
+        .args(["show", "mail"])
+        .assert()
+        .stdout("second\nuser: bob\n");
+    sandbox.pass().args(["log", "mail"]).assert().stdout(contains("   1  "));
+
+    sandbox.pass().args(["restore", "mail", "1"]).assert().success();
+    sandbox.pass().arg("mail").assert().stdout("first\n");
+}
+
+#[test]
+fn tags_filter_ls() {
+    let sandbox = Sandbox::init();
+    sandbox.insert("db", "a");
+    sandbox.insert("mail", "b");
+    sandbox.pass().args(["tag", "add", "db", "prod", "ops"]).assert().success();
+    sandbox.pass().args(["ls", "-t", "prod"]).assert().stdout("db\n");
+    sandbox.pass().args(["tag", "ls", "db"]).assert().stdout(contains("ops"));
+
+    sandbox.pass().args(["tag", "rm", "db", "prod"]).assert().success();
+    sandbox.pass().args(["ls", "-t", "prod"]).assert().stdout("");
+    sandbox
+        .pass()
+        .args(["log", "db"])
+        .assert()
+        .stdout(contains("   1  ").not());
+}
+
+#[test]
+fn attachments_round_trip() {
+    let sandbox = Sandbox::init();
+    sandbox.insert("server", "a");
+    let data: Vec<u8> = (0..=255).cycle().take(5000).collect();
+    fs::write(sandbox.path("key.pem"), &data).unwrap();
+
+    let file = sandbox.path("key.pem");
+    sandbox
+        .pass()
+        .args(["attach", "add", "server"])
+        .arg(&file)
+        .assert()
+        .success();
+    sandbox
+        .pass()
+        .args(["attach", "ls", "server"])
+        .assert()
+        .stdout(contains("key.pem"));
+
+    // an existing file is made private before it is written
+    use std::os::unix::fs::PermissionsExt;
+    let out = sandbox.path("out.pem");
+    fs::write(&out, "old").unwrap();
+    fs::set_permissions(&out, fs::Permissions::from_mode(0o644)).unwrap();
+    sandbox
+        .pass()
+        .args(["attach", "get", "server", "key.pem", "-o"])
+        .arg(&out)
+        .assert()
+        .success();
+    assert_eq!(fs::read(&out).unwrap(), data);
+    let mode = fs::metadata(&out).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[test]
+fn config_set_and_get() {
+    let sandbox = Sandbox::new();
+    sandbox
+        .pass()
+        .args(["config", "set", "clipboard.timeout", "10"])
+        .assert()
+        .success();
+    sandbox
+        .pass()
+        .args(["config", "get", "clipboard.timeout"])
+        .assert()
+        .stdout("10\n");
+    let file = fs::read_to_string(sandbox.path("config.toml")).unwrap();
+    assert!(file.contains("timeout = 10"));
+
+    sandbox
+        .pass()
+        .args(["config", "set", "clipboard.timeout", "soon"])
+        .assert()
+        .stderr(contains("expects a number"));
+}
+
+#[test]
+fn location_shows_its_source() {
+    let sandbox = Sandbox::new();
+    sandbox
+        .pass()
+        .args(["location", "-v"])
+        .assert()
+        .stdout(contains("chosen by env PASS_STORE_DIR"));
+
+    let other = sandbox.path("elsewhere");
+    sandbox
+        .pass()
+        .args(["location", "--store-path"])
+        .arg(&other)
+        .assert()
+        .stdout(contains(other.join("pass.store").to_str().unwrap()));
+}
+
+#[test]
+fn store_needs_its_secret_key() {
+    let sandbox = Sandbox::init();
+    sandbox.insert("mail", "a");
+    sandbox
+        .pass()
+        .env("FAKE_GPG_SECRET_KEYS", "someone@example.com")
+        .arg("mail")
+        .assert()
+        .stdout("")
+        .stderr(contains("No secret key"));
+}
+
+#[test]
+fn corrupt_store_is_an_error() {
+    let sandbox = Sandbox::init();
+    fs::write(sandbox.store_file(), b"pass\x02\x00").unwrap();
+    sandbox
+        .pass()
+        .arg("ls")
+        .assert()
+        .stderr(contains("Failed to read pass.store"));
+
+    fs::write(sandbox.store_file(), b"pass\x09").unwrap();
+    sandbox
+        .pass()
+        .arg("ls")
+        .assert()
+        .stderr(contains("format version 9 is not supported"));
+}
+
+#[test]
+fn password_store_export_and_import() {
+    let sandbox = Sandbox::init();
+    sandbox.insert("web/github", "hunter2");
+    sandbox.edit("web/github", "hunter2\nuser: alice");
+    sandbox.insert("mail", "s3cret");
+
+    let dir = sandbox.path("exported");
+    sandbox
+        .pass()
+        .args(["export", "-f", "password-store", "-o"])
+        .arg(&dir)
+        .assert()
+        .stdout(contains("Exported 2 password(s)"));
+    assert_eq!(fs::read_to_string(dir.join(".gpg-id")).unwrap().trim(), KEY);
+    std::os::unix::fs::symlink(&dir, dir.join("web/loop")).unwrap();
+
+    let other = Sandbox::init();
+    other
+        .pass()
+        .args(["import", "password-store"])
+        .arg(&dir)
+        .assert()
+        .stdout(contains("Imported 2, skipped 0"));
+    other.pass().arg("ls").assert().stdout("mail\nweb/github\n");
+    other.pass().arg("web/github").assert().stdout("hunter2\nuser: alice\n");
+}
+
+#[test]
+fn plaintext_export_needs_confirmation() {
+    let sandbox = Sandbox::init();
+    sandbox.insert("mail", "s3cret");
+    sandbox
+        .pass()
+        .args(["export", "-f", "json"])
+        .write_stdin("n\n")
+        .assert()
+        .stdout(contains("s3cret").not())
+        .stderr(contains("Export cancelled"));
+    sandbox
+        .pass()
+        .args(["export", "-f", "json", "--yes"])
+        .assert()
+        .stdout(contains("\"s3cret\""));
+}
+
+#[test]
+fn audit_reports_reuse() {
+    let sandbox = Sandbox::init();
+    sandbox.insert("a", "correct horse battery staple");
+    sandbox.insert("b", "correct horse battery staple");
+    sandbox.pass().args(["audit", "--json"]).assert().stdout(
+        contains("\"checked\": 2")
+            .and(contains("\"reused\": [\n    [\n      \"a\",\n      \"b\"")),
+    );
+}
+
+#[test]
+fn due_lists_expired_passwords() {
+    let sandbox = Sandbox::init();
+    sandbox.insert("old", "a");
+    sandbox.edit("old", "a\nexpires: 2001-01-01");
+    sandbox.insert("fresh", "b");
+    sandbox
+        .pass()
+        .arg("due")
+        .assert()
+        .stdout(contains("old").and(contains("fresh").not()));
+}
+
+#[test]
+fn hidden_key_id() {
+    let sandbox = Sandbox::new();
+    sandbox.pass().args(["init", "--hide-key-id", KEY]).assert().success();
+    sandbox.insert("mail", "s3cret");
+    let contains_key = |sandbox: &Sandbox| {
+        let bytes = fs::read(sandbox.store_file()).unwrap();
+        bytes.windows(KEY.len()).any(|v| v == KEY.as_bytes())
+    };
+    assert!(!contains_key(&sandbox));
+    sandbox.pass().arg("mail").assert().stdout("s3cret\n");
+
+    // stays hidden when the store is written again
+    sandbox.insert("web", "hunter2");
+    assert!(!contains_key(&sandbox));
+    sandbox
+        .pass()
+        .env("FAKE_GPG_SECRET_KEYS", "someone@example.com")
+        .arg("mail")
+        .assert()
+        .stderr(contains("No secret key"));
+
+    sandbox.pass().args(["re-init", "--show-key-id", KEY]).assert().success();
+    assert!(contains_key(&sandbox));
+    sandbox.pass().arg("web").assert().stdout("hunter2\n");
+}
+
+#[test]
+fn hotp_counter_is_not_versioned() {
+    let sandbox = Sandbox::init();
+    sandbox.insert("bank", "a");
+    let uri = "otpauth://hotp/bank?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+               &counter=0";
+    sandbox.pass().args(["otp", "insert", "bank", uri]).assert().success();
+    let log = sandbox.pass().args(["log", "bank"]).output().unwrap().stdout;
+
+    sandbox.pass().args(["otp", "bank"]).assert().stdout("755224\n");
+    sandbox.pass().args(["otp", "bank"]).assert().stdout("287082\n");
+    sandbox.pass().args(["log", "bank"]).assert().stdout(log);
+}
+
+#[test]
+fn cp_copies_current_values() {
+    let sandbox = Sandbox::init();
+    let work = sandbox.path("work");
+    fs::write(
+        sandbox.path("config.toml"),
+        format!("[stores]\nwork = \"{}\"\n", work.display()),
+    )
+    .unwrap();
+    sandbox.pass().args(["--store", "work", "init", KEY]).assert().success();
+    sandbox.insert("mail", "first");
+    sandbox.edit("mail", "second\ntags: ops");
+    fs::write(sandbox.path("key.pem"), "data").unwrap();
+    sandbox
+        .pass()
+        .args(["attach", "add", "mail"])
+        .arg(sandbox.path("key.pem"))
+        .assert()
+        .success();
+    let work = || {
+        let mut cmd = sandbox.pass();
+        cmd.args(["--store", "work"]);
+        cmd
+    };
+
+    sandbox
+        .pass()
+        .args(["cp", "mail", "--to-store", "work"])
+        .assert()
+        .stdout(contains("Copied 1 password(s)"));
+    work().arg("mail").assert().stdout("second\ntags: ops\n");
+    work().args(["log", "mail"]).assert().stdout(contains("   1  ").not());
+    work()
+        .args(["attach", "ls", "mail"])
+        .assert()
+        .stdout(contains("key.pem").not());
+
+    sandbox
+        .pass()
+        .args(["cp", "mail", "--to-store", "work"])
+        .assert()
+        .stderr(contains("already has [mail]"));
+    sandbox
+        .pass()
+        .args(["cp", "-f", "mail", "--to-store", "work"])
+        .args(["--with-history", "--with-attachments"])
+        .assert()
+        .success();
+    work().args(["log", "mail"]).assert().stdout(contains("   1  "));
+    work().args(["attach", "ls", "mail"]).assert().stdout(contains("key.pem"));
+    work().arg("trash").assert().stdout(contains("mail"));
+}
+
+#[test]
+fn agent_is_only_used_when_enabled() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let sandbox = Sandbox::init();
+    sandbox.insert("mail", "a");
+    let socket = sandbox.path("run/pass-agent.sock");
+    let listener = UnixListener::bind(socket).unwrap();
+    let contacts = Arc::new(AtomicUsize::new(0));
+    let counted = Arc::clone(&contacts);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            drop(stream);
+            counted.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    sandbox.pass().arg("mail").assert().stdout("a\n");
+    assert_eq!(contacts.load(Ordering::SeqCst), 0);
+
+    // a runtime dir others can enter is refused
+    let open = fs::Permissions::from_mode(0o755);
+    fs::set_permissions(sandbox.path("run"), open).unwrap();
+    let enabled = || {
+        let mut cmd = sandbox.pass();
+        cmd.env("PASS_AGENT_ENABLED", "true");
+        cmd
+    };
+    enabled().arg("mail").assert().stdout("a\n");
+    assert_eq!(contacts.load(Ordering::SeqCst), 0);
+
+    let private = fs::Permissions::from_mode(0o700);
+    fs::set_permissions(sandbox.path("run"), private).unwrap();
+    enabled().arg("mail").assert().stdout("a\n");
+    assert!(contacts.load(Ordering::SeqCst) > 0);
+}
@@ -0,0 +1,87 @@
+//! Shared setup for the integration tests: a throwaway home for each test
+//! and the fake gpg in `tests/fake-gpg`, so nothing touches the user's
+//! keyring, config or running agent.
+
+#![allow(dead_code)]
+
+use assert_cmd::Command;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Key id the fake gpg holds a secret key for
+pub const KEY: &str = "test@example.com";
+
+pub fn fake_gpg() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fake-gpg")
+}
+
+/// A temporary directory holding a store, config file, runtime dir and
+/// temp dir for one test.
+pub struct Sandbox {
+    pub dir: TempDir,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        for sub in ["run", "tmp"] {
+            std::fs::create_dir(dir.path().join(sub)).unwrap();
+        }
+        use std::os::unix::fs::PermissionsExt;
+        let private = std::fs::Permissions::from_mode(0o700);
+        std::fs::set_permissions(dir.path().join("run"), private).unwrap();
+        Self { dir }
+    }
+
+    /// A sandbox with a store owned by `KEY`
+    pub fn init() -> Self {
+        let sandbox = Self::new();
+        sandbox.pass().args(["init", KEY]).assert().success();
+        sandbox
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    pub fn store_file(&self) -> PathBuf {
+        self.path("store/pass.store")
+    }
+
+    /// The `pass` binary, set up to use this sandbox and print passwords
+    /// instead of copying them.
+    pub fn pass(&self) -> Command {
+        let mut cmd = Command::cargo_bin("pass").unwrap();
+        for (var, _) in std::env::vars() {
+            if var.starts_with("PASS_") || var == "PASSWORD_STORE_DIR" {
+                cmd.env_remove(var);
+            }
+        }
+        cmd.env("PASS_GPG", fake_gpg())
+            .env("FAKE_GPG_SECRET_KEYS", KEY)
+            .env("PASS_CONFIG", self.path("config.toml"))
+            .env("PASS_STORE_DIR", self.path("store"))
+            .env("PASS_OUTPUT", "print")
+            .env("XDG_RUNTIME_DIR", self.path("run"))
+            .env("TMPDIR", self.path("tmp"))
+            .env("HOME", self.dir.path())
+            .env_remove("EDITOR");
+        cmd
+    }
+
+    /// Insert `password` under `name`
+    pub fn insert(&self, name: &str, password: &str) {
+        self.pass().args(["insert", "-p", password, name]).assert().success();
+    }
+
+    /// Replace the value of `name` with `value`, through the editor
+    pub fn edit(&self, name: &str, value: &str) {
+        let content = self.path("edit-content");
+        std::fs::write(&content, value).unwrap();
+        self.pass()
+            .env("PASS_EDITOR", format!("cp {}", content.display()))
+            .args(["edit", name])
+            .assert()
+            .success();
+    }
+}
@@ -0,0 +1,183 @@
+//! Reading and writing stores through `Database`, with the fake gpg.
+
+mod common;
+
+use common::KEY;
+use pass::internal::{Database, Header, SizedWrite};
+use pass::Error;
+
+use chacha20::cipher::StreamCipher;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard, Once};
+
+/// Gpg decrypts through a fixed file in the temp dir, so stores are
+/// read one test at a time.
+static GPG: Mutex<()> = Mutex::new(());
+
+fn setup() -> MutexGuard<'static, ()> {
+    static ENV: Once = Once::new();
+    ENV.call_once(|| {
+        let run = tempfile::tempdir().unwrap().into_path();
+        std::env::set_var("PASS_GPG", common::fake_gpg());
+        std::env::set_var("FAKE_GPG_SECRET_KEYS", KEY);
+        // keep away from a running agent
+        std::env::set_var("XDG_RUNTIME_DIR", run);
+    });
+    GPG.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn fake_encrypt(data: &[u8]) -> Vec<u8> {
+    [format!("fake-gpg {KEY}\n").as_bytes(), data].concat()
+}
+
+fn sample() -> Database {
+    let mut db = Database::new(Some(KEY.to_string()));
+    db.insert("mail", "s3cret\nuser: bob");
+    db.insert("web/github", "hunter2");
+    db.update("web/github", "hunter3");
+    db.remove("mail");
+    db.insert("bank", "1234");
+    db
+}
+
+fn assert_same(a: &Database, b: &Database) {
+    assert_eq!(a.gpg_id(), b.gpg_id());
+    let (mut names_a, mut names_b) = (a.list_all(), b.list_all());
+    names_a.sort();
+    names_b.sort();
+    assert_eq!(names_a, names_b);
+    for name in names_a {
+        let (x, y) = (a.entry(&name).unwrap(), b.entry(&name).unwrap());
+        assert_eq!(x.value(), y.value());
+        assert_eq!(x.modified(), y.modified());
+        assert_eq!(x.version_count(), y.version_count());
+    }
+    assert_eq!(a.trash().len(), b.trash().len());
+}
+
+#[test]
+fn write_then_read() {
+    let _lock = setup();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nested/store/pass.store");
+
+    let db = sample();
+    db.write_to_file(&path).unwrap();
+    let read = Database::read_from_file(&path).unwrap();
+    assert_same(&db, &read);
+    assert_eq!(read.get("web/github").unwrap(), "hunter3");
+    assert_eq!(read.trash().len(), 1);
+
+    let bytes = fs::read(&path).unwrap();
+    assert_eq!(&bytes[..5], b"pass\x02");
+    let body = String::from_utf8_lossy(&bytes);
+    assert!(!body.contains("hunter3"));
+}
+
+#[test]
+fn reads_format_version_1() {
+    let _lock = setup();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pass.store");
+
+    let header = Header::generate();
+    let mut file = vec![];
+    file.sized_write(KEY.as_bytes()).unwrap();
+    file.sized_write(&fake_encrypt(header.as_bytes())).unwrap();
+    let mut body = serde_json::to_vec(&sample()).unwrap();
+    header.cipher().apply_keystream(&mut body);
+    file.extend_from_slice(&body);
+    fs::write(&path, file).unwrap();
+
+    let read = Database::read_from_file(&path).unwrap();
+    assert_same(&sample(), &read);
+}
+
+#[test]
+fn missing_file() {
+    let _lock = setup();
+    let result = Database::read_from_file(Path::new("/nonexistent/store"));
+    assert!(matches!(result, Err(Error::DataFileNotFound)));
+}
+
+#[test]
+fn rejects_bad_preambles() {
+    let _lock = setup();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pass.store");
+    let read = |bytes: &[u8]| {
+        fs::write(&path, bytes).unwrap();
+        Database::read_from_file(&path)
+    };
+
+    assert!(matches!(read(b"pass\x07"), Err(Error::UnsupportedVersion(7))));
+    assert!(matches!(
+        read(b"pass\x02\xff\xff\xff\xff"),
+        Err(Error::FrameTooLarge { .. })
+    ));
+    assert!(matches!(read(b"pass\x02\x00\x00"), Err(Error::IoError(_))));
+    assert!(matches!(read(b""), Err(Error::IoError(_))));
+
+    // a header of the wrong size
+    let mut bytes = b"pass\x02".to_vec();
+    bytes.sized_write_u32(KEY.as_bytes()).unwrap();
+    bytes.sized_write_u32(&fake_encrypt(b"short")).unwrap();
+    assert!(matches!(read(&bytes), Err(Error::RawBytesReadError)));
+}
+
+#[test]
+fn needs_a_gpg_id_to_write() {
+    let _lock = setup();
+    let dir = tempfile::tempdir().unwrap();
+    let db = Database::new(None);
+    let result = db.write_to_file(&dir.path().join("pass.store"));
+    assert!(matches!(result, Err(Error::GpgIdNotFound)));
+}
+
+#[test]
+fn unreadable_without_the_secret_key() {
+    let _lock = setup();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pass.store");
+    let mut db = sample();
+    db.set_gpg_id("someone@example.com");
+    db.write_to_file(&path).unwrap();
+
+    let result = Database::read_from_file(&path);
+    assert!(matches!(result, Err(Error::IoError(_))));
+}
+
+#[test]
+fn hidden_gpg_id() {
+    let _lock = setup();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pass.store");
+    let mut db = sample();
+    db.set_hide_gpg_id(true);
+    db.write_to_file(&path).unwrap();
+
+    let mut file = fs::File::open(&path).unwrap();
+    let preamble = Database::read_preamble(&mut file).unwrap();
+    assert_eq!(preamble.gpg_id, "");
+    assert!(!preamble.header.windows(KEY.len()).any(|v| v == KEY.as_bytes()));
+
+    let read = Database::read_from_file(&path).unwrap();
+    assert!(read.hides_gpg_id());
+    assert_same(&db, &read);
+
+    // stores written before the id was kept in the body still read
+    let header = Header::generate();
+    let mut file = b"pass\x02".to_vec();
+    file.sized_write_u32(KEY.as_bytes()).unwrap();
+    file.sized_write_u32(&fake_encrypt(header.as_bytes())).unwrap();
+    let mut body = serde_json::to_vec(&Database::new(None)).unwrap();
+    assert!(!String::from_utf8_lossy(&body).contains(KEY));
+    header.cipher().apply_keystream(&mut body);
+    file.extend_from_slice(&body);
+    fs::write(&path, file).unwrap();
+
+    let read = Database::read_from_file(&path).unwrap();
+    assert!(!read.hides_gpg_id());
+    assert_eq!(read.gpg_id().unwrap(), KEY);
+}
@@ -0,0 +1,43 @@
+use pass::internal::{Header, HEADER_BYTE_LEN};
+use pass::Error;
+
+fn bytes() -> [u8; HEADER_BYTE_LEN] {
+    std::array::from_fn(|i| i as u8)
+}
+
+#[test]
+fn round_trips_through_bytes() {
+    let header = Header::from(bytes());
+    assert_eq!(header.as_bytes(), bytes());
+
+    let again = Header::try_from(header.as_bytes()).unwrap();
+    assert_eq!(again.as_bytes(), header.as_bytes());
+    let again = Header::try_from(&header.as_bytes().to_vec()).unwrap();
+    assert_eq!(again.as_bytes(), header.as_bytes());
+}
+
+#[test]
+fn splits_into_key_and_nonce() {
+    let header = Header::from(bytes());
+    assert_eq!(&header.chacha_key()[..], &bytes()[..32]);
+    assert_eq!(header.chacha_nonce(), bytes()[32..]);
+
+    let key = header.chacha_key();
+    let rebuilt = Header::new(&key, &header.chacha_nonce());
+    assert_eq!(rebuilt.as_bytes(), header.as_bytes());
+}
+
+#[test]
+fn rejects_wrong_lengths() {
+    for len in [0, 1, HEADER_BYTE_LEN - 1, HEADER_BYTE_LEN + 1] {
+        let result = Header::try_from(&vec![0u8; len][..]);
+        assert!(matches!(result, Err(Error::RawBytesReadError)), "{len}");
+    }
+}
+
+#[test]
+fn generates_distinct_headers() {
+    let (a, b) = (Header::generate(), Header::generate());
+    assert_ne!(a.as_bytes(), b.as_bytes());
+    assert_ne!(a.as_bytes(), [0; HEADER_BYTE_LEN]);
+}
@@ -0,0 +1,203 @@
+//! Property tests for the I/O adapters, fed through readers and writers
+//! that move a few bytes at a time and get interrupted.
+
+use pass::internal::{ChaReader, ChaWriter, Header};
+use pass::internal::{SizedRead, SizedWrite};
+
+use chacha20::cipher::StreamCipher;
+use proptest::prelude::*;
+use std::io::{self, Read, Write};
+
+/// Hands out `data` in chunks of the given sizes, in turn. A size of 0
+/// is an `Interrupted` error.
+struct ChunkedReader {
+    data: Vec<u8>,
+    pos: usize,
+    sizes: Vec<usize>,
+    turn: usize,
+}
+
+impl ChunkedReader {
+    fn new(data: Vec<u8>, sizes: Vec<usize>) -> Self {
+        Self { data, pos: 0, sizes, turn: 0 }
+    }
+}
+
+impl Read for ChunkedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.sizes[self.turn % self.sizes.len()];
+        self.turn += 1;
+        if size == 0 {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        let n = size.min(buf.len()).min(self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Accepts writes in chunks of the given sizes, in turn. A size of 0 is
+/// an `Interrupted` error.
+struct ChunkedWriter {
+    data: Vec<u8>,
+    sizes: Vec<usize>,
+    turn: usize,
+}
+
+impl ChunkedWriter {
+    fn new(sizes: Vec<usize>) -> Self {
+        Self { data: vec![], sizes, turn: 0 }
+    }
+}
+
+impl Write for ChunkedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let size = self.sizes[self.turn % self.sizes.len()];
+        self.turn += 1;
+        if size == 0 {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        let n = size.min(buf.len());
+        self.data.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Chunk sizes that always make progress eventually
+fn sizes() -> impl Strategy<Value = Vec<usize>> {
+    prop::collection::vec(0usize..17, 1..8)
+        .prop_filter("needs a non-zero size", |v| v.iter().any(|&n| n > 0))
+}
+
+fn header(seed: u8) -> Header {
+    Header::from([seed; 44])
+}
+
+/// `data` encrypted in one go
+fn encrypted(data: &[u8], seed: u8) -> Vec<u8> {
+    let mut data = data.to_vec();
+    header(seed).cipher().apply_keystream(&mut data);
+    data
+}
+
+proptest! {
+    #[test]
+    fn sized_frames_round_trip(
+        frames in prop::collection::vec(
+            prop::collection::vec(any::<u8>(), 0..300),
+            0..6,
+        ),
+        read_sizes in sizes(),
+        write_sizes in sizes(),
+    ) {
+        let mut writer = ChunkedWriter::new(write_sizes);
+        for frame in &frames {
+            writer.sized_write(frame).unwrap();
+            writer.sized_write_u32(frame).unwrap();
+        }
+        let mut reader = ChunkedReader::new(writer.data, read_sizes);
+        for frame in &frames {
+            prop_assert_eq!(&reader.sized_read().unwrap(), frame);
+            prop_assert_eq!(&reader.sized_read_u32(300).unwrap(), frame);
+        }
+        let mut rest = vec![];
+        reader.read_to_end(&mut rest).unwrap();
+        prop_assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn truncated_frames_are_errors(
+        frame in prop::collection::vec(any::<u8>(), 1..300),
+        cut in any::<prop::sample::Index>(),
+        sizes in sizes(),
+    ) {
+        let mut data = vec![];
+        data.sized_write(&frame).unwrap();
+        data.truncate(cut.index(data.len()));
+        let mut reader = ChunkedReader::new(data.clone(), sizes.clone());
+        let e = reader.sized_read().unwrap_err();
+        prop_assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut data = vec![];
+        data.sized_write_u32(&frame).unwrap();
+        data.truncate(cut.index(data.len()));
+        let mut reader = ChunkedReader::new(data, sizes);
+        let e = reader.sized_read_u32(300).unwrap_err();
+        prop_assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected(
+        len in 301u32..,
+        sizes in sizes(),
+    ) {
+        let mut reader = ChunkedReader::new(len.to_be_bytes().to_vec(), sizes);
+        let e = reader.sized_read_u32(300).unwrap_err();
+        prop_assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        let e = pass::Error::from(e);
+        let oversized = matches!(
+            e,
+            pass::Error::FrameTooLarge { len: v, max: 300 } if v == len as u64
+        );
+        prop_assert!(oversized);
+    }
+
+    #[test]
+    fn cha_reader_survives_short_reads(
+        data in prop::collection::vec(any::<u8>(), 0..2000),
+        seed in any::<u8>(),
+        sizes in sizes(),
+    ) {
+        let source = ChunkedReader::new(encrypted(&data, seed), sizes);
+        let mut decrypted = vec![];
+        ChaReader::new(source, header(seed).cipher())
+            .read_to_end(&mut decrypted)
+            .unwrap();
+        prop_assert_eq!(decrypted, data);
+    }
+
+    #[test]
+    fn cha_writer_survives_short_writes(
+        chunks in prop::collection::vec(
+            prop::collection::vec(any::<u8>(), 0..200),
+            0..10,
+        ),
+        seed in any::<u8>(),
+        sizes in sizes(),
+    ) {
+        let mut dst = ChunkedWriter::new(sizes);
+        let mut writer = ChaWriter::new(&mut dst, header(seed).cipher());
+        for chunk in &chunks {
+            writer.write_all(chunk).unwrap();
+        }
+        prop_assert_eq!(dst.data, encrypted(&chunks.concat(), seed));
+    }
+
+    #[test]
+    fn cha_writer_and_reader_are_symmetric(
+        data in prop::collection::vec(any::<u8>(), 0..2000),
+        seed in any::<u8>(),
+        read_sizes in sizes(),
+        write_sizes in sizes(),
+    ) {
+        let mut dst = ChunkedWriter::new(write_sizes);
+        ChaWriter::new(&mut dst, header(seed).cipher())
+            .write_all(&data)
+            .unwrap();
+        if data.len() > 16 {
+            prop_assert_ne!(&dst.data, &data);
+        }
+
+        let source = ChunkedReader::new(dst.data, read_sizes);
+        let mut decrypted = vec![];
+        ChaReader::new(source, header(seed).cipher())
+            .read_to_end(&mut decrypted)
+            .unwrap();
+        prop_assert_eq!(decrypted, data);
+    }
+}